@@ -1,92 +1,274 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fmt::Formatter;
+
+use serde_derive::{Deserialize, Serialize};
+
 use crate::ast::name_type::NameType;
 
 pub mod name_type;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Ast {
   TitleDef(Name),
   NameDef(Name),
   Arrow(Arrow),
   Line(Line),
+  Include(String),
+  SettingDef(Setting),
   Comment(String),
   Empty,
   Documents(Vec<Ast>),
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Caption {
+  texts: BTreeMap<String, String>,
+}
+
+impl Caption {
+  pub const NEUTRAL: &'static str = "";
+
+  pub fn new(text: String) -> Self {
+    Self::default().with_locale(Self::NEUTRAL.to_string(), text)
+  }
+
+  pub fn with_locale(mut self, locale: String, text: String) -> Self {
+    self.texts.insert(locale, text);
+    self
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.texts.is_empty()
+  }
+
+  pub fn get(&self, locale: &str) -> Option<&str> {
+    self.texts.get(locale).map(String::as_str)
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+    self.texts.iter().map(|(locale, text)| (locale.as_str(), text.as_str()))
+  }
+
+  pub fn resolve(&self, locales: &[String]) -> Option<&str> {
+    locales
+      .iter()
+      .find_map(|locale| self.get(locale))
+      .or_else(|| self.get(Self::NEUTRAL))
+      .or_else(|| self.texts.values().next().map(String::as_str))
+  }
+
+  pub fn map<F: Fn(&str) -> String>(&self, f: F) -> Self {
+    Self {
+      texts: self
+        .texts
+        .iter()
+        .map(|(locale, text)| (locale.clone(), f(text)))
+        .collect(),
+    }
+  }
+}
+
+impl From<Option<String>> for Caption {
+  fn from(caption: Option<String>) -> Self {
+    caption.map(Caption::new).unwrap_or_default()
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Name {
+  #[serde(rename = "type")]
   pub name_type: NameType,
   pub name: String,
-  pub caption: Option<String>,
+  #[serde(default, skip_serializing_if = "Caption::is_empty")]
+  pub caption: Caption,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub attributes: Vec<Attribute>,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub fields: Vec<Field>,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub tags: Vec<String>,
 }
 
 impl Name {
-  pub fn of_title(name: String, caption: Option<String>) -> Self {
+  pub fn with_attributes(mut self, attributes: Vec<Attribute>) -> Self {
+    self.attributes = attributes;
+    self
+  }
+
+  pub fn with_fields(mut self, fields: Vec<Field>) -> Self {
+    self.fields = fields;
+    self
+  }
+
+  pub fn with_tags(mut self, tags: Vec<String>) -> Self {
+    self.tags = tags;
+    self
+  }
+
+  pub fn attribute(&self, key: &str) -> Option<&str> {
+    self
+      .attributes
+      .iter()
+      .find(|attribute| attribute.key == key)
+      .map(|attribute| attribute.value.as_str())
+  }
+
+  pub fn of_title(name: String, caption: Caption) -> Self {
     Self {
       name_type: NameType::Title,
       name,
       caption,
+      attributes: Vec::new(),
+      fields: Vec::new(),
+      tags: Vec::new(),
     }
   }
 
-  pub fn of_user(name: String, caption: Option<String>) -> Self {
+  pub fn of_user(name: String, caption: Caption) -> Self {
     Self {
       name_type: NameType::User,
       name,
       caption,
+      attributes: Vec::new(),
+      fields: Vec::new(),
+      tags: Vec::new(),
     }
   }
 
-  pub fn of_command(name: String, caption: Option<String>) -> Self {
+  pub fn of_command(name: String, caption: Caption) -> Self {
     Self {
       name_type: NameType::Command,
       name,
       caption,
+      attributes: Vec::new(),
+      fields: Vec::new(),
+      tags: Vec::new(),
     }
   }
 
-  pub fn of_event(name: String, caption: Option<String>) -> Self {
+  pub fn of_event(name: String, caption: Caption) -> Self {
     Self {
       name_type: NameType::Event,
       name,
       caption,
+      attributes: Vec::new(),
+      fields: Vec::new(),
+      tags: Vec::new(),
     }
   }
 
-  pub fn of_aggregate(name: String, caption: Option<String>) -> Self {
+  pub fn of_aggregate(name: String, caption: Caption) -> Self {
     Self {
       name_type: NameType::Aggregate,
       name,
       caption,
+      attributes: Vec::new(),
+      fields: Vec::new(),
+      tags: Vec::new(),
     }
   }
 
-  pub fn of_policy(name: String, caption: Option<String>) -> Self {
+  pub fn of_policy(name: String, caption: Caption) -> Self {
     Self {
       name_type: NameType::Policy,
       name,
       caption,
+      attributes: Vec::new(),
+      fields: Vec::new(),
+      tags: Vec::new(),
     }
   }
 
-  pub fn of_read_model(name: String, caption: Option<String>) -> Self {
+  pub fn of_read_model(name: String, caption: Caption) -> Self {
     Self {
       name_type: NameType::ReadModel,
       name,
       caption,
+      attributes: Vec::new(),
+      fields: Vec::new(),
+      tags: Vec::new(),
+    }
+  }
+
+  pub fn of_hot_spot(name: String, caption: Caption) -> Self {
+    Self {
+      name_type: NameType::HotSpot,
+      name,
+      caption,
+      attributes: Vec::new(),
+      fields: Vec::new(),
+      tags: Vec::new(),
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Attribute {
+  pub key: String,
+  pub value: String,
+}
+
+impl Attribute {
+  pub fn new(key: String, value: String) -> Self {
+    Self { key, value }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldType {
+  Simple(String),
+  List(Box<FieldType>),
+}
+
+impl fmt::Display for FieldType {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match self {
+      FieldType::Simple(name) => write!(f, "{}", name),
+      FieldType::List(element_type) => write!(f, "[{}]", element_type),
     }
   }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Field {
+  pub name: String,
+  pub field_type: FieldType,
+}
+
+impl Field {
+  pub fn new(name: String, field_type: FieldType) -> Self {
+    Self { name, field_type }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Setting {
+  pub key: String,
+  pub value: String,
+}
+
+impl Setting {
+  pub fn new(key: String, value: String) -> Self {
+    Self { key, value }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Arrow {
+  #[serde(rename = "from")]
   pub from_ref: String,
+  #[serde(rename = "to")]
   pub to_ref: String,
-  pub caption: Option<String>,
+  #[serde(default, skip_serializing_if = "Caption::is_empty")]
+  pub caption: Caption,
 }
 
 impl Arrow {
-  pub fn new(from_ref: String, to_ref: String, caption: Option<String>) -> Self {
+  pub fn new(from_ref: String, to_ref: String, caption: Caption) -> Self {
     if from_ref.is_empty() {
       panic!("from_ref must not be empty");
     }
@@ -101,15 +283,18 @@ impl Arrow {
   }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Line {
+  #[serde(rename = "from")]
   pub from_ref: String,
+  #[serde(rename = "to")]
   pub to_ref: String,
-  pub caption: Option<String>,
+  #[serde(default, skip_serializing_if = "Caption::is_empty")]
+  pub caption: Caption,
 }
 
 impl Line {
-  pub fn new(from_ref: String, to_ref: String, caption: Option<String>) -> Self {
+  pub fn new(from_ref: String, to_ref: String, caption: Caption) -> Self {
     if from_ref.is_empty() {
       panic!("from_ref must not be empty");
     }
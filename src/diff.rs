@@ -0,0 +1,524 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fmt::Formatter;
+
+use serde_derive::Serialize;
+
+use crate::ast::{Arrow, Ast, Caption, Line, Name};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffStatus {
+  Added,
+  Removed,
+  Changed,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edge {
+  pub from: String,
+  pub to: String,
+  pub directed: bool,
+  pub caption: Caption,
+}
+
+impl Edge {
+  // A line has no direction, so `A -- B` and `B -- A` are the same edge.
+  fn connects(&self, from: &str, to: &str, directed: bool) -> bool {
+    self.directed == directed
+      && ((self.from == from && self.to == to) || (!directed && self.from == to && self.to == from))
+  }
+
+  fn same_endpoints(&self, other: &Edge) -> bool {
+    self.connects(&other.from, &other.to, other.directed)
+  }
+
+  fn to_ast(&self) -> Ast {
+    if self.directed {
+      Ast::Arrow(Arrow::new(self.from.clone(), self.to.clone(), self.caption.clone()))
+    } else {
+      Ast::Line(Line::new(self.from.clone(), self.to.clone(), self.caption.clone()))
+    }
+  }
+}
+
+impl fmt::Display for Edge {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    let connector = if self.directed { "->" } else { "--" };
+    write!(f, "{} {} {}", self.from, connector, self.to)
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change<T> {
+  Added(T),
+  Removed(T),
+  Changed { before: T, after: T },
+}
+
+impl<T> Change<T> {
+  pub fn status(&self) -> DiffStatus {
+    match self {
+      Change::Added(_) => DiffStatus::Added,
+      Change::Removed(_) => DiffStatus::Removed,
+      Change::Changed { .. } => DiffStatus::Changed,
+    }
+  }
+
+  pub fn current(&self) -> &T {
+    match self {
+      Change::Added(item) | Change::Removed(item) => item,
+      Change::Changed { after, .. } => after,
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ModelDiff {
+  pub elements: Vec<Change<Name>>,
+  pub edges: Vec<Change<Edge>>,
+  // every edge of the combined model in order, so that duplicated edges get their own status
+  combined_edges: Vec<(Edge, Option<DiffStatus>)>,
+}
+
+fn collect<'a>(ast: &'a Ast, names: &mut Vec<&'a Name>, edges: &mut Vec<Edge>) {
+  match ast {
+    Ast::NameDef(name) if names.iter().all(|n| n.name != name.name) => names.push(name),
+    Ast::Arrow(arrow) => edges.push(Edge {
+      from: arrow.from_ref.clone(),
+      to: arrow.to_ref.clone(),
+      directed: true,
+      caption: arrow.caption.clone(),
+    }),
+    Ast::Line(line) => edges.push(Edge {
+      from: line.from_ref.clone(),
+      to: line.to_ref.clone(),
+      directed: false,
+      caption: line.caption.clone(),
+    }),
+    Ast::Documents(documents) => {
+      for document in documents {
+        collect(document, names, edges);
+      }
+    }
+    _ => {}
+  }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ElementRef {
+  pub name: String,
+  #[serde(rename = "type")]
+  pub element_type: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Retyped {
+  pub name: String,
+  pub before: String,
+  pub after: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CaptionChange {
+  pub target: String,
+  pub before: BTreeMap<String, String>,
+  pub after: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EdgeRef {
+  pub from: String,
+  pub to: String,
+  pub directed: bool,
+}
+
+#[derive(Debug, Serialize, Default)]
+pub struct DiffReport {
+  pub added_elements: Vec<ElementRef>,
+  pub removed_elements: Vec<ElementRef>,
+  pub retyped_elements: Vec<Retyped>,
+  pub caption_changes: Vec<CaptionChange>,
+  pub added_edges: Vec<EdgeRef>,
+  pub removed_edges: Vec<EdgeRef>,
+}
+
+impl From<&Name> for ElementRef {
+  fn from(name: &Name) -> Self {
+    Self {
+      name: name.name.clone(),
+      element_type: name.name_type.to_string(),
+    }
+  }
+}
+
+impl From<&Edge> for EdgeRef {
+  fn from(edge: &Edge) -> Self {
+    Self {
+      from: edge.from.clone(),
+      to: edge.to.clone(),
+      directed: edge.directed,
+    }
+  }
+}
+
+impl CaptionChange {
+  fn new(target: String, before: &Caption, after: &Caption) -> Self {
+    let texts = |caption: &Caption| {
+      caption
+        .iter()
+        .map(|(locale, text)| (locale.to_string(), text.to_string()))
+        .collect()
+    };
+    Self {
+      target,
+      before: texts(before),
+      after: texts(after),
+    }
+  }
+}
+
+fn caption_text(caption: &Caption) -> String {
+  caption.resolve(&[]).unwrap_or_default().to_string()
+}
+
+impl ModelDiff {
+  pub fn new(before: &Ast, after: &Ast) -> Self {
+    let (mut before_names, mut before_edges) = (Vec::new(), Vec::new());
+    collect(before, &mut before_names, &mut before_edges);
+    let (mut after_names, mut after_edges) = (Vec::new(), Vec::new());
+    collect(after, &mut after_names, &mut after_edges);
+
+    let mut elements = Vec::new();
+    for name in &after_names {
+      match before_names.iter().find(|before| before.name == name.name) {
+        None => elements.push(Change::Added((*name).clone())),
+        Some(before) if before.name_type != name.name_type || before.caption != name.caption => {
+          elements.push(Change::Changed {
+            before: (*before).clone(),
+            after: (*name).clone(),
+          })
+        }
+        Some(_) => {}
+      }
+    }
+    for name in &before_names {
+      if after_names.iter().all(|after| after.name != name.name) {
+        elements.push(Change::Removed((*name).clone()));
+      }
+    }
+
+    // Each old edge is paired with at most one new edge, preferring one with the same caption, so that
+    // duplicated edges are matched one to one.
+    let mut edges = Vec::new();
+    let mut combined_edges = Vec::new();
+    let mut unmatched = before_edges.iter().map(Some).collect::<Vec<_>>();
+    for edge in &after_edges {
+      let position = |same_caption: bool| {
+        unmatched.iter().position(|before| {
+          before.is_some_and(|before| before.same_endpoints(edge) && (!same_caption || before.caption == edge.caption))
+        })
+      };
+      let change = match position(true).or_else(|| position(false)) {
+        None => Some(Change::Added(edge.clone())),
+        Some(index) => {
+          let before = unmatched[index].take().unwrap();
+          (before.caption != edge.caption).then(|| Change::Changed {
+            before: before.clone(),
+            after: edge.clone(),
+          })
+        }
+      };
+      combined_edges.push((edge.clone(), change.as_ref().map(Change::status)));
+      edges.extend(change);
+    }
+    for edge in unmatched.into_iter().flatten() {
+      combined_edges.push((edge.clone(), Some(DiffStatus::Removed)));
+      edges.push(Change::Removed(edge.clone()));
+    }
+    Self {
+      elements,
+      edges,
+      combined_edges,
+    }
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.elements.is_empty() && self.edges.is_empty()
+  }
+
+  pub fn element_status(&self, name: &str) -> Option<DiffStatus> {
+    self
+      .elements
+      .iter()
+      .find(|change| change.current().name == name)
+      .map(Change::status)
+  }
+
+  // `occurrence` counts the earlier edges of the combined model with the same kind and ends. Captions
+  // are not compared, as numbering rewrites them before rendering.
+  pub fn edge_status(&self, from: &str, to: &str, directed: bool, occurrence: usize) -> Option<DiffStatus> {
+    self
+      .combined_edges
+      .iter()
+      .filter(|(edge, _)| edge.connects(from, to, directed))
+      .nth(occurrence)
+      .and_then(|(_, status)| status.clone())
+  }
+
+  pub fn report(&self) -> DiffReport {
+    let mut report = DiffReport::default();
+    for change in &self.elements {
+      match change {
+        Change::Added(name) => report.added_elements.push(ElementRef::from(name)),
+        Change::Removed(name) => report.removed_elements.push(ElementRef::from(name)),
+        Change::Changed { before, after } => {
+          if before.name_type != after.name_type {
+            report.retyped_elements.push(Retyped {
+              name: after.name.clone(),
+              before: before.name_type.to_string(),
+              after: after.name_type.to_string(),
+            });
+          }
+          if before.caption != after.caption {
+            report
+              .caption_changes
+              .push(CaptionChange::new(after.name.clone(), &before.caption, &after.caption));
+          }
+        }
+      }
+    }
+    for change in &self.edges {
+      match change {
+        Change::Added(edge) => report.added_edges.push(EdgeRef::from(edge)),
+        Change::Removed(edge) => report.removed_edges.push(EdgeRef::from(edge)),
+        Change::Changed { before, after } => {
+          report
+            .caption_changes
+            .push(CaptionChange::new(after.to_string(), &before.caption, &after.caption));
+        }
+      }
+    }
+    report
+  }
+
+  pub fn to_json(&self) -> serde_json::Result<String> {
+    serde_json::to_string_pretty(&self.report())
+  }
+
+  // The combined model is the new model followed by everything that was removed from the old one.
+  pub fn combined(&self, after: &Ast) -> Ast {
+    let mut documents = vec![after.clone()];
+    for change in &self.elements {
+      if let Change::Removed(name) = change {
+        documents.push(Ast::NameDef(name.clone()));
+      }
+    }
+    for change in &self.edges {
+      if let Change::Removed(edge) = change {
+        documents.push(edge.to_ast());
+      }
+    }
+    Ast::Documents(documents)
+  }
+}
+
+impl fmt::Display for ModelDiff {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    if self.is_empty() {
+      return writeln!(f, "no changes");
+    }
+    for change in &self.elements {
+      match change {
+        Change::Added(name) => writeln!(f, "+ {} {}", name.name_type, name.name)?,
+        Change::Removed(name) => writeln!(f, "- {} {}", name.name_type, name.name)?,
+        Change::Changed { before, after } => {
+          if before.name_type != after.name_type {
+            writeln!(f, "~ {} type: {} -> {}", after.name, before.name_type, after.name_type)?;
+          }
+          if before.caption != after.caption {
+            writeln!(
+              f,
+              "~ {} caption: \"{}\" -> \"{}\"",
+              after.name,
+              caption_text(&before.caption),
+              caption_text(&after.caption)
+            )?;
+          }
+        }
+      }
+    }
+    for change in &self.edges {
+      match change {
+        Change::Added(edge) => writeln!(f, "+ {}", edge)?,
+        Change::Removed(edge) => writeln!(f, "- {}", edge)?,
+        Change::Changed { before, after } => writeln!(
+          f,
+          "~ {} caption: \"{}\" -> \"{}\"",
+          after,
+          caption_text(&before.caption),
+          caption_text(&after.caption)
+        )?,
+      }
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parsers::parse;
+
+  fn before() -> Ast {
+    parse(
+      br#"
+        c:OrderProduct
+        a:Order
+        e:ProductOrdered:"ordered"
+        r:OrderHistory
+        p:NotifyPolicy
+        OrderProduct->Order
+        Order->ProductOrdered
+        ProductOrdered--OrderHistory
+        "#,
+    )
+    .unwrap()
+  }
+
+  #[test]
+  fn test_diff() {
+    let after = parse(
+      br#"
+        c:OrderProduct
+        a:Order
+        e:ProductOrdered:"placed"
+        e:OrderHistory
+        e:OrderShipped
+        OrderProduct->Order
+        Order->ProductOrdered:"1"
+        Order->OrderShipped
+        "#,
+    )
+    .unwrap();
+    let diff = ModelDiff::new(&before(), &after);
+    assert_eq!(diff.element_status("ProductOrdered"), Some(DiffStatus::Changed));
+    assert_eq!(diff.element_status("OrderHistory"), Some(DiffStatus::Changed));
+    assert_eq!(diff.element_status("OrderShipped"), Some(DiffStatus::Added));
+    assert_eq!(diff.element_status("NotifyPolicy"), Some(DiffStatus::Removed));
+    assert_eq!(diff.element_status("Order"), None);
+    assert_eq!(
+      diff.edge_status("Order", "ProductOrdered", true, 0),
+      Some(DiffStatus::Changed)
+    );
+    assert_eq!(
+      diff.edge_status("ProductOrdered", "OrderHistory", false, 0),
+      Some(DiffStatus::Removed)
+    );
+    assert_eq!(diff.edge_status("OrderProduct", "Order", true, 0), None);
+    assert_eq!(
+      diff.to_string(),
+      concat!(
+        "~ ProductOrdered caption: \"ordered\" -> \"placed\"\n",
+        "~ OrderHistory type: ReadModel -> Event\n",
+        "+ Event OrderShipped\n",
+        "- Policy NotifyPolicy\n",
+        "~ Order -> ProductOrdered caption: \"\" -> \"1\"\n",
+        "+ Order -> OrderShipped\n",
+        "- ProductOrdered -- OrderHistory\n",
+      )
+    );
+  }
+
+  #[test]
+  fn test_to_json() {
+    let after = parse(
+      r#"
+        c:OrderProduct
+        a:Order
+        e:ProductOrdered:ja"注文された":en"ordered"
+        e:OrderHistory
+        OrderProduct->Order
+        Order->ProductOrdered
+        Order->OrderHistory
+        "#
+      .as_bytes(),
+    )
+    .unwrap();
+    let json = ModelDiff::new(&before(), &after).to_json().unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(
+      value,
+      serde_json::json!({
+        "added_elements": [],
+        "removed_elements": [{"name": "NotifyPolicy", "type": "Policy"}],
+        "retyped_elements": [{"name": "OrderHistory", "before": "ReadModel", "after": "Event"}],
+        "caption_changes": [{
+          "target": "ProductOrdered",
+          "before": {"": "ordered"},
+          "after": {"ja": "注文された", "en": "ordered"}
+        }],
+        "added_edges": [{"from": "Order", "to": "OrderHistory", "directed": true}],
+        "removed_edges": [{"from": "ProductOrdered", "to": "OrderHistory", "directed": false}]
+      })
+    );
+  }
+
+  #[test]
+  fn test_combined() {
+    let after = parse(b"c:OrderProduct\na:Order\nOrderProduct->Order\n").unwrap();
+    let diff = ModelDiff::new(&before(), &after);
+    match diff.combined(&after) {
+      Ast::Documents(documents) => assert_eq!(documents.len(), 1 + 3 + 2),
+      other => panic!("unexpected: {:?}", other),
+    }
+  }
+
+  #[test]
+  fn test_reversed_lines_and_duplicated_edges() {
+    let after = parse(
+      br#"
+        c:OrderProduct
+        a:Order
+        e:ProductOrdered:"ordered"
+        r:OrderHistory
+        p:NotifyPolicy
+        OrderProduct->Order
+        OrderProduct->Order
+        Order->ProductOrdered
+        OrderHistory--ProductOrdered
+        "#,
+    )
+    .unwrap();
+    let diff = ModelDiff::new(&before(), &after);
+    assert_eq!(diff.to_string(), "+ OrderProduct -> Order\n");
+    let diff = ModelDiff::new(&after, &before());
+    assert_eq!(diff.to_string(), "- OrderProduct -> Order\n");
+    // only the second of the duplicated arrows is drawn as removed
+    assert_eq!(diff.edge_status("OrderProduct", "Order", true, 0), None);
+    assert_eq!(
+      diff.edge_status("OrderProduct", "Order", true, 1),
+      Some(DiffStatus::Removed)
+    );
+    assert_eq!(diff.edge_status("ProductOrdered", "OrderHistory", false, 0), None);
+  }
+
+  #[test]
+  fn test_reordered_model_has_no_diff() {
+    let after = parse(
+      br#"
+        Order->ProductOrdered
+        ProductOrdered -- OrderHistory
+        OrderProduct->Order
+        p:NotifyPolicy
+        r:OrderHistory
+        e:ProductOrdered : "ordered"
+        a:Order
+        c:OrderProduct
+        "#,
+    )
+    .unwrap();
+    let diff = ModelDiff::new(&before(), &after);
+    assert!(diff.is_empty());
+    assert_eq!(diff.to_string(), "no changes\n");
+  }
+}
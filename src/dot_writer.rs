@@ -1,24 +1,39 @@
 extern crate handlebars;
 extern crate serde;
 
+mod drawio;
+mod excalidraw;
+mod markup;
+mod viewer;
+
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::Write;
 use std::str::FromStr;
 
-use graphviz_rust::dot_structures::Graph;
 use graphviz_rust::{
   cmd::{CommandArg, Format},
   exec_dot,
-  printer::{DotPrinter, PrinterContext},
 };
 use handlebars::{to_json, Handlebars};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
 use serde_derive::Serialize;
 use serde_json::value::{Map, Value as Json};
 
 use crate::ast::name_type::NameType;
-use crate::ast::Ast;
-
-#[derive(Serialize)]
+use crate::ast::{Ast, Attribute, Caption, Field, Name, Setting};
+use crate::diff::{DiffStatus, ModelDiff};
+use crate::layout::{grid, timeline, Cell, Lane};
+use crate::numbering::number_edges;
+use crate::settings::{hex_color, LabelDisplay, Layout, NodeLabel, Settings, SettingsError, TagFilterMode};
+use crate::tag_expr::TagExpr;
+
+use self::drawio::render_drawio;
+use self::excalidraw::render_excalidraw;
+use self::markup::{escape_html, rich_label, to_html};
+use self::viewer::render_html;
+
+#[derive(Serialize, PartialEq)]
 pub enum NodeType {
   User,
   Command,
@@ -56,36 +71,255 @@ impl From<&NameType> for NodeType {
 pub struct Title {
   name: String,
   caption: Option<String>,
+  label: String,
 }
 
 impl Title {
   pub fn new(name: String, caption: Option<String>) -> Self {
-    Self { name, caption }
+    let label = escape_quoted(caption.as_deref().unwrap_or(&name));
+    Self { name, caption, label }
+  }
+}
+
+#[derive(Serialize)]
+pub struct NodeAttribute {
+  key: String,
+  value: String,
+}
+
+impl NodeAttribute {
+  pub fn new(key: String, value: String) -> Self {
+    Self { key, value }
+  }
+}
+
+impl From<&Attribute> for NodeAttribute {
+  fn from(attribute: &Attribute) -> Self {
+    NodeAttribute::new(attribute.key.clone(), attribute.value.clone())
+  }
+}
+
+#[derive(Serialize)]
+pub struct NodeField {
+  name: String,
+  field_type: String,
+}
+
+impl NodeField {
+  pub fn new(name: String, field_type: String) -> Self {
+    Self { name, field_type }
+  }
+}
+
+impl From<&Field> for NodeField {
+  fn from(field: &Field) -> Self {
+    NodeField::new(field.name.clone(), field.field_type.to_string())
+  }
+}
+
+#[derive(Serialize)]
+pub struct DiffStyle {
+  color: String,
+  style: String,
+  penwidth: u32,
+}
+
+impl DiffStyle {
+  pub fn new(status: &DiffStatus, filled: bool) -> Self {
+    let (color, style, penwidth) = match status {
+      DiffStatus::Added => ("green3", "bold", 2),
+      DiffStatus::Removed => ("red", "dashed", 2),
+      DiffStatus::Changed => ("blue", "bold", 3),
+    };
+    Self {
+      color: color.to_string(),
+      style: if filled {
+        format!("filled,{}", style)
+      } else {
+        style.to_string()
+      },
+      penwidth,
+    }
+  }
+
+  // The draw.io and Excalidraw exports draw items marked by `diff` with the colour, width and
+  // dashes of their Graphviz style: `(hex colour, width, dashed)`.
+  fn stroke(&self) -> (String, u32, bool) {
+    (hex_color(&self.color), self.penwidth, self.style.contains("dashed"))
   }
 }
 
+const LINK_ATTRIBUTES: [&str; 2] = ["url", "tooltip"];
+// everything but the unreserved characters of RFC 3986, so that a name stays a single path segment
+const URL_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'.').remove(b'_').remove(b'~');
+
+fn escape_quoted(s: &str) -> String {
+  s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+// Labels stay plain text for the other exporters and are escaped only where the template quotes them.
+fn serialize_quoted<S: serde::Serializer>(label: &Option<String>, serializer: S) -> Result<S::Ok, S::Error> {
+  serde::Serialize::serialize(&label.as_deref().map(escape_quoted), serializer)
+}
+
 #[derive(Serialize)]
 pub struct Node {
   name: String,
   node_type: NodeType,
   shape: String,
   fill_color: String,
+  #[serde(serialize_with = "serialize_quoted")]
   label: Option<String>,
+  #[serde(skip)]
+  caption: Option<String>,
+  attributes: Vec<NodeAttribute>,
+  fields: Vec<NodeField>,
+  html_label: Option<String>,
+  url: Option<String>,
+  tooltip: Option<String>,
+  dimmed: bool,
+  diff_style: Option<DiffStyle>,
 }
 
 impl Node {
-  pub fn new(name: String, node_type: NodeType, shape: String, fill_color: String, label: Option<String>) -> Self {
+  pub fn new(
+    name: String,
+    node_type: NodeType,
+    shape: String,
+    fill_color: String,
+    label: Option<String>,
+    attributes: Vec<NodeAttribute>,
+  ) -> Self {
+    let html_label = label.as_deref().and_then(rich_label);
     Self {
       name,
       node_type,
       shape,
       fill_color,
       label,
+      caption: None,
+      attributes,
+      fields: Vec::new(),
+      html_label,
+      url: None,
+      tooltip: None,
+      dimmed: false,
+      diff_style: None,
     }
   }
+
+  pub fn with_dimmed(mut self, dimmed: bool) -> Self {
+    self.dimmed = dimmed;
+    self
+  }
+
+  pub fn with_diff_style(mut self, diff_style: Option<DiffStyle>) -> Self {
+    self.diff_style = diff_style;
+    self
+  }
+
+  // Without an explicit tooltip the attributes are listed one per line.
+  pub fn with_link(mut self, url: Option<String>, tooltip: Option<String>) -> Self {
+    self.url = url.as_deref().map(escape_quoted);
+    self.tooltip = match tooltip {
+      Some(tooltip) => Some(escape_quoted(&tooltip)),
+      None if !self.attributes.is_empty() => Some(
+        self
+          .attributes
+          .iter()
+          .map(|attribute| {
+            format!(
+              "{}: {}\\n",
+              escape_quoted(&attribute.key),
+              escape_quoted(&attribute.value)
+            )
+          })
+          .collect(),
+      ),
+      None => None,
+    };
+    self
+  }
+
+  pub fn with_fields(mut self, fields: Vec<NodeField>) -> Self {
+    self.fields = fields;
+    if !self.fields.is_empty() {
+      self.html_label = Some(self.record_label());
+    }
+    self
+  }
+
+  pub fn with_caption(mut self, caption: Option<String>) -> Self {
+    self.caption = caption;
+    self
+  }
+
+  pub fn with_table_label(mut self, type_name: &str, show_attributes: bool) -> Self {
+    self.html_label = Some(self.table_label(type_name, show_attributes));
+    self
+  }
+
+  fn table_label(&self, type_name: &str, show_attributes: bool) -> String {
+    let mut rows = vec![
+      format!(
+        r#"<TR><TD><FONT POINT-SIZE="9">«{}»</FONT></TD></TR>"#,
+        escape_html(type_name)
+      ),
+      format!("<TR><TD><B>{}</B></TD></TR>", escape_html(&self.name)),
+    ];
+    // the caption row follows the display mode, which leaves the label out when only names are shown
+    if let Some(caption) = self.caption.as_ref().filter(|_| self.label.is_some()) {
+      rows.push(format!("<TR><TD>{}</TD></TR>", to_html(caption)));
+    }
+    let attributes = if show_attributes {
+      self.attributes.as_slice()
+    } else {
+      &[]
+    };
+    let properties = attributes
+      .iter()
+      .map(|attribute| (&attribute.key, &attribute.value))
+      .chain(self.fields.iter().map(|field| (&field.name, &field.field_type)))
+      .map(|(key, value)| {
+        format!(
+          r#"<TR><TD ALIGN="LEFT">{}: {}</TD></TR>"#,
+          escape_html(key),
+          escape_html(value)
+        )
+      })
+      .collect::<Vec<_>>();
+    if !properties.is_empty() {
+      rows.push("<HR/>".to_string());
+      rows.extend(properties);
+    }
+    format!(
+      r#"<TABLE BORDER="0" CELLBORDER="0" CELLSPACING="0">{}</TABLE>"#,
+      rows.concat()
+    )
+  }
+
+  fn record_label(&self) -> String {
+    let header = self.label.as_ref().unwrap_or(&self.name);
+    let rows = self
+      .fields
+      .iter()
+      .map(|field| {
+        format!(
+          r#"<TR><TD ALIGN="LEFT">{}: {}</TD></TR>"#,
+          escape_html(&field.name),
+          escape_html(&field.field_type)
+        )
+      })
+      .collect::<String>();
+    format!(
+      r#"<TABLE BORDER="0" CELLBORDER="0" CELLSPACING="0"><TR><TD><B>{}</B></TD></TR><HR/>{}</TABLE>"#,
+      to_html(header),
+      rows
+    )
+  }
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, PartialEq)]
 pub enum EdgeType {
   Arrow,
   Line,
@@ -96,16 +330,156 @@ pub struct Edge {
   edge_type: EdgeType,
   from: String,
   to: String,
+  #[serde(serialize_with = "serialize_quoted")]
   label: Option<String>,
+  html_label: Option<String>,
+  dimmed: bool,
+  diff_style: Option<DiffStyle>,
 }
 
 impl Edge {
   pub fn new(edge_type: EdgeType, from: String, to: String, label: Option<String>) -> Self {
+    let html_label = label.as_deref().and_then(rich_label);
     Self {
       edge_type,
       from,
       to,
       label,
+      html_label,
+      dimmed: false,
+      diff_style: None,
+    }
+  }
+
+  pub fn with_dimmed(mut self, dimmed: bool) -> Self {
+    self.dimmed = dimmed;
+    self
+  }
+
+  pub fn with_diff_style(mut self, diff_style: Option<DiffStyle>) -> Self {
+    self.diff_style = diff_style;
+    self
+  }
+}
+
+#[derive(Serialize)]
+pub struct GraphStyle {
+  rankdir: String,
+  splines: Option<String>,
+  bg_color: String,
+  font_color: String,
+  line_color: String,
+  dimmed_color: String,
+  labelloc: Option<String>,
+}
+
+impl From<&Settings> for GraphStyle {
+  fn from(settings: &Settings) -> Self {
+    Self {
+      rankdir: match settings.layout {
+        Layout::Default => settings.direction.to_string(),
+        Layout::Timeline => settings.direction.across().to_string(),
+      },
+      splines: settings.splines.as_ref().map(|splines| splines.to_string()),
+      bg_color: settings.theme.background_color().to_string(),
+      font_color: settings.theme.font_color().to_string(),
+      line_color: settings.theme.line_color().to_string(),
+      dimmed_color: settings.theme.dimmed_color().to_string(),
+      labelloc: settings.title_position.labelloc().map(str::to_string),
+    }
+  }
+}
+
+#[derive(Serialize)]
+pub struct TimelineLink {
+  from: String,
+  to: String,
+}
+
+impl TimelineLink {
+  pub fn new(from: String, to: String) -> Self {
+    Self { from, to }
+  }
+}
+
+#[derive(Serialize)]
+pub struct TimelineStyle {
+  lanes: Vec<Vec<String>>,
+  links: Vec<TimelineLink>,
+}
+
+#[derive(Serialize)]
+pub struct LegendNode {
+  name: String,
+  shape: String,
+  fill_color: String,
+}
+
+#[derive(Serialize)]
+pub struct LegendEdge {
+  name: String,
+  directed: bool,
+}
+
+#[derive(Serialize)]
+pub struct LegendStyle {
+  nodes: Vec<LegendNode>,
+  edges: Vec<LegendEdge>,
+}
+
+// The grid exports only draw the nodes placed by `grid_positions`.
+fn warn_undrawn_edge(edge: &Edge) {
+  log::warn!(
+    "skipping edge {} -> {} to an element that is not drawn",
+    edge.from,
+    edge.to
+  );
+}
+
+// Places the nodes on the lane grid; lanes without nodes are left out and nodes that are not on the
+// grid go to an extra row at the bottom.
+fn grid_positions(nodes: &[Node], cells: &[Cell]) -> HashMap<String, (usize, usize)> {
+  let rendered = |cell: &&Cell| nodes.iter().any(|node| node.name == cell.name);
+  let lanes = Lane::all()
+    .into_iter()
+    .filter(|lane| cells.iter().filter(rendered).any(|cell| cell.lane == *lane))
+    .collect::<Vec<_>>();
+  let mut positions = cells
+    .iter()
+    .filter(rendered)
+    .map(|cell| {
+      let row = lanes.iter().position(|lane| *lane == cell.lane).unwrap_or_default();
+      (cell.name.clone(), (row, cell.column))
+    })
+    .collect::<HashMap<_, _>>();
+  let mut column = 0;
+  for node in nodes {
+    if !positions.contains_key(&node.name) {
+      positions.insert(node.name.clone(), (lanes.len(), column));
+      column += 1;
+    }
+  }
+  positions
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OutputFormat {
+  Svg,
+  Html,
+  Drawio,
+  Excalidraw,
+}
+
+impl FromStr for OutputFormat {
+  type Err = ();
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "svg" => Ok(OutputFormat::Svg),
+      "html" => Ok(OutputFormat::Html),
+      "drawio" => Ok(OutputFormat::Drawio),
+      "excalidraw" => Ok(OutputFormat::Excalidraw),
+      _ => Err(()),
     }
   }
 }
@@ -114,6 +488,18 @@ pub struct DotWriter {
   context: Map<String, Json>,
   nodes: Vec<Node>,
   edges: Vec<Edge>,
+  settings: Settings,
+  overrides: Vec<Setting>,
+  matched_names: Option<HashSet<String>>,
+  format: OutputFormat,
+  diff: Option<ModelDiff>,
+  edge_occurrences: HashMap<(String, String, bool), usize>,
+}
+
+impl Default for DotWriter {
+  fn default() -> Self {
+    Self::new()
+  }
 }
 
 impl DotWriter {
@@ -122,9 +508,30 @@ impl DotWriter {
       context: Map::new(),
       nodes: Vec::new(),
       edges: Vec::new(),
+      settings: Settings::default(),
+      overrides: Vec::new(),
+      matched_names: None,
+      format: OutputFormat::Svg,
+      diff: None,
+      edge_occurrences: HashMap::new(),
     }
   }
 
+  pub fn with_overrides(mut self, overrides: Vec<Setting>) -> Self {
+    self.overrides = overrides;
+    self
+  }
+
+  pub fn with_format(mut self, format: OutputFormat) -> Self {
+    self.format = format;
+    self
+  }
+
+  pub fn with_diff(mut self, diff: ModelDiff) -> Self {
+    self.diff = Some(diff);
+    self
+  }
+
   fn add_node(&mut self, node: Node) {
     self.nodes.push(node);
     let value = self.context.entry("nodes".to_string()).or_insert(to_json(&self.nodes));
@@ -139,16 +546,25 @@ impl DotWriter {
 
   fn get_dot_string_from_hbs(&self) -> Result<String, Box<dyn std::error::Error>> {
     let mut handlebars = Handlebars::new();
-    handlebars.register_template_file("template", "template.hbs").unwrap();
-    let out = handlebars.render("template", &self.context)?;
+    handlebars.register_template_string("template", include_str!("../template.hbs"))?;
+    let mut context = self.context.clone();
+    context.insert("graph".to_string(), to_json(GraphStyle::from(&self.settings)));
+    let out = handlebars.render("template", &context)?;
     Ok(out)
   }
 
-  fn get_parsed_dot_string(&self, g: &Graph) -> Result<String, Box<dyn std::error::Error>> {
-    let mut printer_context = PrinterContext::default();
-    printer_context.with_semi();
-    let dot = g.print(&mut printer_context);
-    Ok(dot)
+  fn get_drawio_string(&self, ast: &Ast) -> String {
+    let title = self
+      .context
+      .get("title")
+      .and_then(|title| title.get("name"))
+      .and_then(Json::as_str)
+      .unwrap_or("event-grapher");
+    render_drawio(title, &self.nodes, &self.edges, &grid(ast), &self.settings.theme)
+  }
+
+  fn get_excalidraw_string(&self, ast: &Ast) -> Result<String, serde_json::Error> {
+    render_excalidraw(&self.nodes, &self.edges, &grid(ast), &self.settings.theme)
   }
 
   fn exec_dot(&self, dot_string: String, fmt: Format, output: String) -> Result<(), Box<dyn std::error::Error>> {
@@ -158,89 +574,337 @@ impl DotWriter {
 
   fn fill_color(&self, ast: &Ast) -> String {
     match ast {
-      Ast::NameDef(name) => match name.name_type {
-        NameType::User => "lightyellow".to_string(),
-        NameType::Command => "lightblue".to_string(),
-        NameType::Event => "lightyellow".to_string(),
-        NameType::Aggregate => "palegreen".to_string(),
-        NameType::Policy => "plum".to_string(),
-        NameType::ReadModel => "lightgreen".to_string(),
-        NameType::HotSpot => "lightpink".to_string(),
-        _ => panic!("fill_color"),
-      },
+      Ast::NameDef(name) => self.settings.theme.fill_color(&name.name_type).to_string(),
       _ => panic!("fill_color"),
     }
   }
 
-  fn shape(&self, ast: &Ast) -> String {
-    match ast {
-      Ast::NameDef(name) => match name.name_type {
-        NameType::User => "box".to_string(),
-        NameType::Command => "box".to_string(),
-        NameType::Event => "box".to_string(),
-        NameType::Aggregate => "box".to_string(),
-        NameType::Policy => "box".to_string(),
-        NameType::ReadModel => "box".to_string(),
-        NameType::HotSpot => "box".to_string(),
-        _ => panic!("shape"),
-      },
+  fn shape(&self, name_type: &NameType) -> String {
+    match name_type {
+      NameType::User => "box".to_string(),
+      NameType::Command => "box".to_string(),
+      NameType::Event => "box".to_string(),
+      NameType::Aggregate => "box".to_string(),
+      NameType::Policy => "box".to_string(),
+      NameType::ReadModel => "box".to_string(),
+      NameType::HotSpot => "box".to_string(),
       _ => panic!("shape"),
     }
   }
 
+  fn apply_settings(&mut self, ast: &Ast) -> Result<(), SettingsError> {
+    match ast {
+      Ast::SettingDef(setting) => self.settings.apply(setting),
+      Ast::Documents(documents) => documents.iter().try_for_each(|document| self.apply_settings(document)),
+      _ => Ok(()),
+    }
+  }
+
+  fn collect_matched_names(ast: &Ast, tag_filter: &TagExpr, matched_names: &mut HashSet<String>) {
+    match ast {
+      Ast::NameDef(name) if tag_filter.matches(name) => {
+        matched_names.insert(name.name.clone());
+      }
+      Ast::Documents(documents) => {
+        for document in documents {
+          Self::collect_matched_names(document, tag_filter, matched_names);
+        }
+      }
+      _ => {}
+    }
+  }
+
+  fn configure(&mut self, ast: &Ast) -> Result<(), SettingsError> {
+    self.apply_settings(ast)?;
+    for setting in &self.overrides {
+      self.settings.apply(setting)?;
+    }
+    self.matched_names = self.settings.tag_filter.as_ref().map(|tag_filter| {
+      let mut matched_names = HashSet::new();
+      Self::collect_matched_names(ast, tag_filter, &mut matched_names);
+      matched_names
+    });
+    Ok(())
+  }
+
+  fn is_matched(&self, name: &str) -> bool {
+    self
+      .matched_names
+      .as_ref()
+      .is_none_or(|matched_names| matched_names.contains(name))
+  }
+
+  fn is_hidden(&self, matched: bool) -> bool {
+    !matched && self.settings.tag_filter_mode == TagFilterMode::Hide
+  }
+
+  fn eval_edge(&mut self, edge_type: EdgeType, from: &str, to: &str, caption: &Caption) {
+    let matched = self.is_matched(from) && self.is_matched(to);
+    if self.is_hidden(matched) {
+      return;
+    }
+    let directed = edge_type == EdgeType::Arrow;
+    let ends = if directed || from <= to { (from, to) } else { (to, from) };
+    let occurrence = self
+      .edge_occurrences
+      .entry((ends.0.to_string(), ends.1.to_string(), directed))
+      .or_default();
+    *occurrence += 1;
+    let occurrence = *occurrence - 1;
+    let diff_style = self
+      .diff
+      .as_ref()
+      .and_then(|diff| diff.edge_status(from, to, directed, occurrence))
+      .map(|status| DiffStyle::new(&status, false));
+    self.add_edge(
+      Edge::new(edge_type, from.to_string(), to.to_string(), self.caption(caption))
+        .with_dimmed(!matched)
+        .with_diff_style(diff_style),
+    );
+  }
+
+  fn url(&self, name: &Name) -> Option<String> {
+    name.attribute("url").map(str::to_string).or_else(|| {
+      self.settings.url_pattern.as_ref().map(|pattern| {
+        pattern
+          .replace(
+            "{type}",
+            &utf8_percent_encode(&name.name_type.to_string().to_lowercase(), URL_SEGMENT).to_string(),
+          )
+          .replace("{name}", &utf8_percent_encode(&name.name, URL_SEGMENT).to_string())
+      })
+    })
+  }
+
+  fn localized(&self, caption: &Caption) -> Option<String> {
+    caption.resolve(&self.settings.locales).map(str::to_string)
+  }
+
+  fn caption(&self, caption: &Caption) -> Option<String> {
+    match self.settings.label_display {
+      LabelDisplay::Name => None,
+      LabelDisplay::Caption | LabelDisplay::Both => self.localized(caption),
+    }
+  }
+
+  fn label(&self, name: &str, caption: &Caption) -> Option<String> {
+    match (&self.settings.label_display, self.localized(caption)) {
+      (LabelDisplay::Both, Some(caption)) => Some(format!("{}\n{}", name, caption)),
+      _ => self.caption(caption),
+    }
+  }
+
   fn eval_ast(&mut self, ast: &Ast) {
     match ast {
       Ast::TitleDef(title) => {
-        let title = Title::new(title.name.clone(), title.caption.clone());
+        let title = Title::new(title.name.clone(), self.localized(&title.caption));
         self.context.insert("title".to_string(), to_json(title));
       }
       Ast::NameDef(name) => {
-        self.add_node(Node::new(
+        let matched = self.is_matched(&name.name);
+        if self.is_hidden(matched) {
+          return;
+        }
+        let fill_color = if matched {
+          self.fill_color(ast)
+        } else {
+          self.settings.theme.dimmed_fill_color().to_string()
+        };
+        let mut node = Node::new(
           name.name.clone(),
           NodeType::from(&name.name_type),
-          self.shape(ast),
-          self.fill_color(ast),
-          name.caption.clone(),
-        ));
+          self.shape(&name.name_type),
+          fill_color,
+          self.label(&name.name, &name.caption),
+          name
+            .attributes
+            .iter()
+            .filter(|attribute| !LINK_ATTRIBUTES.contains(&attribute.key.as_str()))
+            .map(NodeAttribute::from)
+            .collect(),
+        )
+        .with_caption(self.localized(&name.caption))
+        .with_link(self.url(name), name.attribute("tooltip").map(str::to_string))
+        .with_diff_style(
+          self
+            .diff
+            .as_ref()
+            .and_then(|diff| diff.element_status(&name.name))
+            .map(|status| DiffStyle::new(&status, true)),
+        )
+        .with_fields(name.fields.iter().map(NodeField::from).collect())
+        .with_dimmed(!matched);
+        if self.settings.node_label == NodeLabel::Table {
+          node = node.with_table_label(&name.name_type.to_string(), self.settings.show_attributes);
+        }
+        self.add_node(node);
       }
       Ast::Arrow(arrow) => {
-        self.add_edge(Edge::new(
-          EdgeType::Arrow,
-          arrow.from_ref.clone(),
-          arrow.to_ref.clone(),
-          arrow.caption.clone(),
-        ));
+        self.eval_edge(EdgeType::Arrow, &arrow.from_ref, &arrow.to_ref, &arrow.caption);
       }
       Ast::Line(line) => {
-        self.add_edge(Edge::new(
-          EdgeType::Line,
-          line.from_ref.clone(),
-          line.to_ref.clone(),
-          line.caption.clone(),
-        ));
+        self.eval_edge(EdgeType::Line, &line.from_ref, &line.to_ref, &line.caption);
       }
       Ast::Documents(documents) => {
         for document in documents {
           self.eval_ast(document);
         }
       }
-      Ast::Comment(comment) => {}
+      Ast::Include(path) => {
+        log::warn!("unresolved include: {}", path);
+      }
+      Ast::SettingDef(_) => {}
+      Ast::Comment(_) => {}
       Ast::Empty => {}
     }
   }
 
+  fn timeline_style(&self, ast: &Ast) -> TimelineStyle {
+    let rendered = self.nodes.iter().map(|node| node.name.as_str()).collect::<HashSet<_>>();
+    let mut lanes = Vec::new();
+    let mut links = Vec::new();
+    for (lane, names) in timeline(ast).lanes {
+      let mut names = names
+        .into_iter()
+        .filter(|name| rendered.contains(name.as_str()))
+        .collect::<Vec<_>>();
+      if names.is_empty() {
+        continue;
+      }
+      if lane == Lane::Events {
+        if self.settings.direction.is_reversed() {
+          names.reverse();
+        }
+        links.extend(
+          names
+            .windows(2)
+            .map(|pair| TimelineLink::new(pair[0].clone(), pair[1].clone())),
+        );
+      }
+      lanes.push(names);
+    }
+    links.extend(
+      lanes
+        .windows(2)
+        .map(|pair| TimelineLink::new(pair[0][0].clone(), pair[1][0].clone())),
+    );
+    TimelineStyle { lanes, links }
+  }
+
+  fn collect_name_types(ast: &Ast, names: &HashSet<&str>, name_types: &mut Vec<NameType>) {
+    match ast {
+      Ast::NameDef(name) if names.contains(name.name.as_str()) && !name_types.contains(&name.name_type) => {
+        name_types.push(name.name_type.clone());
+      }
+      Ast::Documents(documents) => {
+        for document in documents {
+          Self::collect_name_types(document, names, name_types);
+        }
+      }
+      _ => {}
+    }
+  }
+
+  fn legend_style(&self, ast: &Ast) -> LegendStyle {
+    let rendered = self.nodes.iter().map(|node| node.name.as_str()).collect::<HashSet<_>>();
+    let mut name_types = Vec::new();
+    Self::collect_name_types(ast, &rendered, &mut name_types);
+    let nodes = [
+      NameType::User,
+      NameType::Command,
+      NameType::Aggregate,
+      NameType::Event,
+      NameType::Policy,
+      NameType::ReadModel,
+      NameType::HotSpot,
+    ]
+    .iter()
+    .filter(|name_type| name_types.contains(name_type))
+    .map(|name_type| LegendNode {
+      name: name_type.to_string(),
+      shape: self.shape(name_type),
+      fill_color: self.settings.theme.fill_color(name_type).to_string(),
+    })
+    .collect();
+    let edges = [(EdgeType::Arrow, "Arrow"), (EdgeType::Line, "Line")]
+      .into_iter()
+      .filter(|(edge_type, _)| self.edges.iter().any(|edge| edge.edge_type == *edge_type))
+      .map(|(edge_type, name)| LegendEdge {
+        name: name.to_string(),
+        directed: edge_type == EdgeType::Arrow,
+      })
+      .collect();
+    LegendStyle { nodes, edges }
+  }
+
+  fn eval_legend(&mut self, ast: &Ast) {
+    if self.settings.show_legend {
+      let legend = self.legend_style(ast);
+      self.context.insert("legend".to_string(), to_json(legend));
+    }
+  }
+
+  fn eval_layout(&mut self, ast: &Ast) {
+    if self.settings.layout == Layout::Timeline {
+      let timeline = self.timeline_style(ast);
+      self.context.insert("timeline".to_string(), to_json(timeline));
+    }
+  }
+
+  // Edges hidden by the tag filter are dropped before numbering, so the steps that are drawn have no
+  // gaps.
+  fn without_hidden_edges(&self, ast: &Ast) -> Ast {
+    let hidden = |from: &str, to: &str| self.is_hidden(self.is_matched(from) && self.is_matched(to));
+    match ast {
+      Ast::Arrow(arrow) if hidden(&arrow.from_ref, &arrow.to_ref) => Ast::Empty,
+      Ast::Line(line) if hidden(&line.from_ref, &line.to_ref) => Ast::Empty,
+      Ast::Documents(documents) => Ast::Documents(
+        documents
+          .iter()
+          .map(|document| self.without_hidden_edges(document))
+          .collect(),
+      ),
+      other => other.clone(),
+    }
+  }
+
+  fn prepare(&mut self, ast: &Ast) -> Result<Ast, SettingsError> {
+    self.configure(ast)?;
+    let ast = number_edges(&self.without_hidden_edges(ast), &self.settings.numbering);
+    self.eval_ast(&ast);
+    self.eval_layout(&ast);
+    self.eval_legend(&ast);
+    Ok(ast)
+  }
+
   pub fn render(&mut self, ast: &Ast, output_file_name: &str) -> Result<(), Box<dyn std::error::Error>> {
-    self.eval_ast(ast);
+    let ast = self.prepare(ast)?;
+
+    // draw.io and Excalidraw files are laid out by our own grid, so neither a .dot file nor Graphviz is needed
+    if self.format == OutputFormat::Drawio {
+      let mut drawio_file = File::create(format!("{}.drawio", output_file_name))?;
+      drawio_file.write_all(self.get_drawio_string(&ast).as_bytes())?;
+      return Ok(());
+    }
+    if self.format == OutputFormat::Excalidraw {
+      let mut excalidraw_file = File::create(format!("{}.excalidraw", output_file_name))?;
+      excalidraw_file.write_all(self.get_excalidraw_string(&ast)?.as_bytes())?;
+      return Ok(());
+    }
 
     let dot_string = self.get_dot_string_from_hbs()?;
-    //log::debug!("dot_string = {}", dot_string);
-    // let graph = graphviz_rust::parse(&dot_string)?;
 
     let mut dot_file = File::create(format!("{}.dot", output_file_name))?;
-    // let parsed_dot_string = self.get_parsed_dot_string(&graph)?;
     dot_file.write_all(dot_string.as_bytes())?;
 
-    self.exec_dot(dot_string, Format::Svg, format!("{}.svg", output_file_name))?;
+    if self.format == OutputFormat::Html {
+      let svg = exec_dot(dot_string, vec![CommandArg::Format(Format::Svg)])?;
+      let html = render_html(&self.context, &svg, &self.nodes, &self.edges)?;
+      let mut html_file = File::create(format!("{}.html", output_file_name))?;
+      html_file.write_all(html.as_bytes())?;
+    } else {
+      self.exec_dot(dot_string, Format::Svg, format!("{}.svg", output_file_name))?;
+    }
 
     Ok(())
   }
@@ -248,7 +912,7 @@ impl DotWriter {
 
 #[cfg(test)]
 pub mod tests {
-  use crate::ast::Name;
+  use crate::ast::{Arrow, FieldType, Name};
   use std::env;
 
   use super::*;
@@ -256,7 +920,7 @@ pub mod tests {
   #[test]
   fn it_works() {
     let mut visitor = DotWriter::new();
-    let ast = Ast::TitleDef(Name::of_title("Test".to_string(), None));
+    let ast = Ast::TitleDef(Name::of_title("Test".to_string(), Caption::default()));
     visitor.eval_ast(&ast);
     assert_eq!(visitor.context.len(), 1);
   }
@@ -267,19 +931,626 @@ pub mod tests {
     let ast = Ast::NameDef(crate::ast::Name {
       name_type: NameType::User,
       name: "Test".to_string(),
-      caption: None,
+      caption: Caption::default(),
+      attributes: vec![],
+      fields: vec![],
+      tags: vec![],
     });
     visitor.eval_ast(&ast);
     assert_eq!(visitor.nodes.len(), 1);
   }
 
+  #[test]
+  fn test_attributes_as_tooltip() {
+    let mut dot_writer = DotWriter::new();
+    let ast = Ast::Documents(vec![
+      Ast::TitleDef(Name::of_title("Test".to_string(), Caption::default())),
+      Ast::NameDef(
+        Name::of_event("ordered".to_string(), Caption::default()).with_attributes(vec![
+          Attribute::new("owner".to_string(), "team-a".to_string()),
+          Attribute::new("sla".to_string(), "5s".to_string()),
+        ]),
+      ),
+    ]);
+    dot_writer.eval_ast(&ast);
+    let dot_string = dot_writer.get_dot_string_from_hbs().unwrap();
+    assert!(dot_string.contains(r#"tooltip="owner: team-a\nsla: 5s\n""#));
+  }
+
+  #[test]
+  fn test_attribute_tooltip_is_dot_escaped() {
+    let mut dot_writer = DotWriter::new();
+    let ast = Ast::NameDef(
+      Name::of_event("ordered".to_string(), Caption::default()).with_attributes(vec![Attribute::new(
+        "path".to_string(),
+        r#"C:\orders "v2""#.to_string(),
+      )]),
+    );
+    dot_writer.eval_ast(&ast);
+    let dot_string = dot_writer.get_dot_string_from_hbs().unwrap();
+    assert!(dot_string.contains(r#"tooltip="path: C:\\orders \"v2\"\n""#));
+  }
+
+  #[test]
+  fn test_labels_are_dot_escaped() {
+    let mut dot_writer = DotWriter::new();
+    let ast = Ast::Documents(vec![
+      Ast::NameDef(Name::of_event(
+        "ordered".to_string(),
+        Caption::new(r#"C:\new x = "y""#.to_string()),
+      )),
+      Ast::NameDef(Name::of_read_model("history".to_string(), Caption::default())),
+      Ast::Arrow(Arrow::new(
+        "ordered".to_string(),
+        "history".to_string(),
+        Caption::new(r"\N = 1".to_string()),
+      )),
+    ]);
+    dot_writer.eval_ast(&ast);
+    let dot_string = dot_writer.get_dot_string_from_hbs().unwrap();
+    assert!(dot_string.contains(r#"label="C:\\new x = \"y\"""#));
+    assert!(dot_string.contains(r#"ordered -> history [label="\\N = 1"];"#));
+  }
+
+  #[test]
+  fn test_links_and_tooltips() {
+    let mut dot_writer = DotWriter::new();
+    let ast = Ast::Documents(vec![
+      Ast::SettingDef(Setting::new(
+        "url".to_string(),
+        "https://wiki/{type}/{name}".to_string(),
+      )),
+      Ast::NameDef(
+        Name::of_event("ordered".to_string(), Caption::default()).with_attributes(vec![
+          Attribute::new("url".to_string(), "https://adr/0001?a=1&b=2".to_string()),
+          Attribute::new("tooltip".to_string(), "see \"ADR 1\"".to_string()),
+          Attribute::new("owner".to_string(), "team-a".to_string()),
+        ]),
+      ),
+      Ast::NameDef(Name::of_read_model("history".to_string(), Caption::default())),
+    ]);
+    dot_writer.configure(&ast).unwrap();
+    dot_writer.eval_ast(&ast);
+    let dot_string = dot_writer.get_dot_string_from_hbs().unwrap();
+    assert!(dot_string.contains(r#"URL="https://adr/0001?a=1&b=2", tooltip="see \"ADR 1\"""#));
+    assert!(dot_string.contains(r#"history [shape=box, fillcolor="lightgreen", URL="https://wiki/readmodel/history"]"#));
+  }
+
+  #[test]
+  fn test_url_pattern_is_percent_encoded() {
+    let mut dot_writer = DotWriter::new();
+    let ast = Ast::Documents(vec![
+      Ast::SettingDef(Setting::new(
+        "url".to_string(),
+        "https://wiki/{type}/{name}".to_string(),
+      )),
+      Ast::NameDef(Name::of_read_model("注文履歴".to_string(), Caption::default())),
+      Ast::NameDef(Name::of_event("a#b?c/d".to_string(), Caption::default())),
+    ]);
+    dot_writer.configure(&ast).unwrap();
+    dot_writer.eval_ast(&ast);
+    let dot_string = dot_writer.get_dot_string_from_hbs().unwrap();
+    assert!(dot_string.contains(r#"URL="https://wiki/readmodel/%E6%B3%A8%E6%96%87%E5%B1%A5%E6%AD%B4""#));
+    assert!(dot_string.contains(r#"URL="https://wiki/event/a%23b%3Fc%2Fd""#));
+  }
+
+  #[test]
+  fn test_settings_with_overrides() {
+    let mut dot_writer = DotWriter::new().with_overrides(vec![Setting::new("theme".to_string(), "light".to_string())]);
+    let ast = Ast::Documents(vec![
+      Ast::SettingDef(Setting::new("direction".to_string(), "TB".to_string())),
+      Ast::SettingDef(Setting::new("theme".to_string(), "dark".to_string())),
+      Ast::SettingDef(Setting::new("splines".to_string(), "ortho".to_string())),
+      Ast::SettingDef(Setting::new("hide".to_string(), "captions".to_string())),
+      Ast::NameDef(Name::of_event(
+        "ordered".to_string(),
+        Caption::new("注文された".to_string()),
+      )),
+    ]);
+    dot_writer.configure(&ast).unwrap();
+    dot_writer.eval_ast(&ast);
+    let dot_string = dot_writer.get_dot_string_from_hbs().unwrap();
+    assert!(dot_string.contains(r#"rankdir = "TB";"#));
+    assert!(dot_string.contains(r#"splines = "ortho";"#));
+    assert!(dot_string.contains(r#"ordered [shape=box, fillcolor="orange"]"#));
+  }
+
+  #[test]
+  fn test_numbering_skips_hidden_edges() {
+    let ast = crate::parsers::parse(
+      br#"
+        !tags mvp
+        !numbering source
+        c:OrderProduct @mvp
+        e:ProductOrdered @mvp
+        e:GiftWrapped
+        r:OrderHistory @mvp
+        OrderProduct->ProductOrdered
+        ProductOrdered->GiftWrapped
+        ProductOrdered->OrderHistory
+        "#,
+    )
+    .unwrap();
+    let mut dot_writer = DotWriter::new();
+    dot_writer.prepare(&ast).unwrap();
+    let dot_string = dot_writer.get_dot_string_from_hbs().unwrap();
+    assert!(dot_string.contains(r#"OrderProduct -> ProductOrdered [label="1"];"#));
+    assert!(dot_string.contains(r#"ProductOrdered -> OrderHistory [label="2"];"#));
+  }
+
+  #[test]
+  fn test_title_position() {
+    let ast = crate::parsers::parse("t:Ordering:\"注文 \\\"v2\\\"\"\ne:ordered\n".as_bytes()).unwrap();
+    let mut dot_writer = DotWriter::new();
+    dot_writer.configure(&ast).unwrap();
+    dot_writer.eval_ast(&ast);
+    assert!(!dot_writer.get_dot_string_from_hbs().unwrap().contains("labelloc"));
+
+    let mut dot_writer = DotWriter::new().with_overrides(vec![Setting::new("title".to_string(), "bottom".to_string())]);
+    dot_writer.configure(&ast).unwrap();
+    dot_writer.eval_ast(&ast);
+    let dot_string = dot_writer.get_dot_string_from_hbs().unwrap();
+    assert!(dot_string.contains(r#"label = "注文 \"v2\"";"#));
+    assert!(dot_string.contains(r#"labelloc = "b";"#));
+  }
+
+  // The baseline template only had cases for commands, events, aggregates, policies and read models
+  // and silently dropped users; they are rendered since the theme-aware template.
+  #[test]
+  fn test_user_nodes_are_rendered() {
+    let ast = crate::parsers::parse(b"u:Customer\nc:PlaceOrder\nCustomer->PlaceOrder\n").unwrap();
+    let mut dot_writer = DotWriter::new();
+    dot_writer.configure(&ast).unwrap();
+    dot_writer.eval_ast(&ast);
+    let dot_string = dot_writer.get_dot_string_from_hbs().unwrap();
+    assert!(dot_string.contains(r#"Customer [shape=box, fillcolor="lightyellow"]; // User"#));
+  }
+
+  #[test]
+  fn test_multi_line_captions_as_html_labels() {
+    let mut dot_writer = DotWriter::new();
+    let ast = Ast::Documents(vec![
+      Ast::NameDef(Name::of_event(
+        "ordered".to_string(),
+        Caption::new("注文\n**された**".to_string()),
+      )),
+      Ast::NameDef(Name::of_event(
+        "shipped".to_string(),
+        Caption::new("出荷された".to_string()),
+      )),
+      Ast::Arrow(crate::ast::Arrow::new(
+        "ordered".to_string(),
+        "shipped".to_string(),
+        Caption::new("1\n2".to_string()),
+      )),
+    ]);
+    dot_writer.eval_ast(&ast);
+    let dot_string = dot_writer.get_dot_string_from_hbs().unwrap();
+    assert!(dot_string.contains("label=<注文<BR/><B>された</B>>"));
+    assert!(dot_string.contains(r#"label="出荷された""#));
+    assert!(dot_string.contains("ordered -> shipped [label=<1<BR/>2>]"));
+  }
+
+  #[test]
+  fn test_timeline_layout() {
+    let mut dot_writer = DotWriter::new();
+    let ast = crate::parsers::parse(
+      br#"
+        !layout timeline
+        e:OrderShipped
+        e:ProductOrdered
+        c:OrderProduct
+        c:ShipOrder
+        a:Order
+        OrderProduct->Order
+        Order->ProductOrdered
+        ShipOrder->Order
+        Order->OrderShipped
+        "#,
+    )
+    .unwrap();
+    dot_writer.configure(&ast).unwrap();
+    dot_writer.eval_ast(&ast);
+    dot_writer.eval_layout(&ast);
+    let dot_string = dot_writer.get_dot_string_from_hbs().unwrap();
+    assert!(dot_string.contains(r#"rankdir = "TB";"#));
+    assert!(dot_string.contains("OrderProduct -> Order [constraint=false, ]"));
+    assert!(dot_string.contains("{ rank = same; ProductOrdered; OrderShipped; }"));
+    assert!(dot_string.contains("ProductOrdered -> OrderShipped [style=invis];"));
+    assert!(dot_string.contains("OrderProduct -> ProductOrdered [style=invis];"));
+    assert!(dot_string.contains("ProductOrdered -> Order [style=invis];"));
+  }
+
+  fn tagged_documents() -> Ast {
+    Ast::Documents(vec![
+      Ast::NameDef(Name::of_command("Order".to_string(), Caption::default()).with_tags(vec!["mvp".to_string()])),
+      Ast::NameDef(Name::of_event("Ordered".to_string(), Caption::default()).with_tags(vec!["mvp".to_string()])),
+      Ast::NameDef(Name::of_event("Shipped".to_string(), Caption::default()).with_tags(vec!["phase2".to_string()])),
+      Ast::Arrow(crate::ast::Arrow::new(
+        "Order".to_string(),
+        "Ordered".to_string(),
+        Caption::default(),
+      )),
+      Ast::Arrow(crate::ast::Arrow::new(
+        "Ordered".to_string(),
+        "Shipped".to_string(),
+        Caption::default(),
+      )),
+    ])
+  }
+
+  #[test]
+  fn test_tag_filter_hides_unmatched_elements() {
+    let mut dot_writer = DotWriter::new().with_overrides(vec![Setting::new("tags".to_string(), "@mvp".to_string())]);
+    let ast = tagged_documents();
+    dot_writer.configure(&ast).unwrap();
+    dot_writer.eval_ast(&ast);
+    let names = dot_writer
+      .nodes
+      .iter()
+      .map(|node| node.name.as_str())
+      .collect::<Vec<_>>();
+    assert_eq!(names, vec!["Order", "Ordered"]);
+    assert_eq!(dot_writer.edges.len(), 1);
+  }
+
+  #[test]
+  fn test_tag_filter_greys_out_unmatched_elements() {
+    let mut dot_writer = DotWriter::new().with_overrides(vec![
+      Setting::new("tags".to_string(), "mvp".to_string()),
+      Setting::new("tag_mode".to_string(), "grey".to_string()),
+    ]);
+    let ast = tagged_documents();
+    dot_writer.configure(&ast).unwrap();
+    dot_writer.eval_ast(&ast);
+    let dimmed = dot_writer.nodes.iter().map(|node| node.dimmed).collect::<Vec<_>>();
+    assert_eq!(dimmed, vec![false, false, true]);
+    let dimmed = dot_writer.edges.iter().map(|edge| edge.dimmed).collect::<Vec<_>>();
+    assert_eq!(dimmed, vec![false, true]);
+    let dot_string = dot_writer.get_dot_string_from_hbs().unwrap();
+    assert!(dot_string.contains(r#"Shipped [shape=box, fillcolor="gray92", color="gray70", fontcolor="gray70"]"#));
+  }
+
+  #[test]
+  fn test_fields_as_record_label() {
+    let mut dot_writer = DotWriter::new();
+    let ast = Ast::NameDef(
+      Name::of_event("OrderPlaced".to_string(), Caption::new("注文された".to_string())).with_fields(vec![
+        Field::new("orderId".to_string(), FieldType::Simple("OrderId".to_string())),
+        Field::new(
+          "items".to_string(),
+          FieldType::List(Box::new(FieldType::Simple("LineItem".to_string()))),
+        ),
+      ]),
+    );
+    dot_writer.eval_ast(&ast);
+    assert_eq!(
+      dot_writer.nodes[0].html_label.as_deref(),
+      Some(concat!(
+        r#"<TABLE BORDER="0" CELLBORDER="0" CELLSPACING="0"><TR><TD><B>注文された</B></TD></TR><HR/>"#,
+        r#"<TR><TD ALIGN="LEFT">orderId: OrderId</TD></TR><TR><TD ALIGN="LEFT">items: [LineItem]</TD></TR></TABLE>"#
+      ))
+    );
+  }
+
+  #[test]
+  fn test_label_display() {
+    let ast = Ast::Documents(vec![
+      Ast::NameDef(Name::of_command(
+        "OrderProduct".to_string(),
+        Caption::new("注文する".to_string()),
+      )),
+      Ast::NameDef(Name::of_event("ProductOrdered".to_string(), Caption::default())),
+      Ast::Arrow(crate::ast::Arrow::new(
+        "OrderProduct".to_string(),
+        "ProductOrdered".to_string(),
+        Caption::new("成功".to_string()),
+      )),
+    ]);
+    let labels = |display: &str| {
+      let mut dot_writer =
+        DotWriter::new().with_overrides(vec![Setting::new("display".to_string(), display.to_string())]);
+      dot_writer.configure(&ast).unwrap();
+      dot_writer.eval_ast(&ast);
+      (
+        dot_writer
+          .nodes
+          .iter()
+          .map(|node| node.label.clone())
+          .collect::<Vec<_>>(),
+        dot_writer.edges[0].label.clone(),
+      )
+    };
+    assert_eq!(labels("name"), (vec![None, None], None));
+    assert_eq!(
+      labels("caption"),
+      (vec![Some("注文する".to_string()), None], Some("成功".to_string()))
+    );
+    assert_eq!(
+      labels("both"),
+      (
+        vec![Some("OrderProduct\n注文する".to_string()), None],
+        Some("成功".to_string())
+      )
+    );
+  }
+
+  #[test]
+  fn test_localized_captions() {
+    let ast = crate::parsers::parse(
+      r#"
+        e:OrderPlaced:ja"注文された":en"Order placed"
+        e:OrderShipped:ja"出荷された"
+        e:OrderCancelled:"キャンセル":fr"Annulée"
+        "#
+      .as_bytes(),
+    )
+    .unwrap();
+    let labels = |locale: &str| {
+      let mut dot_writer =
+        DotWriter::new().with_overrides(vec![Setting::new("locale".to_string(), locale.to_string())]);
+      dot_writer.configure(&ast).unwrap();
+      dot_writer.eval_ast(&ast);
+      dot_writer
+        .nodes
+        .iter()
+        .map(|node| node.label.clone().unwrap())
+        .collect::<Vec<_>>()
+    };
+    assert_eq!(labels("en"), ["Order placed", "出荷された", "キャンセル"]);
+    assert_eq!(labels("fr,ja"), ["注文された", "出荷された", "Annulée"]);
+  }
+
+  #[test]
+  fn test_legend() {
+    let mut dot_writer = DotWriter::new();
+    let ast = crate::parsers::parse(
+      br#"
+        !show legend
+        !theme dark
+        c:OrderProduct
+        e:ProductOrdered
+        r:OrderHistory
+        OrderProduct->ProductOrdered
+        ProductOrdered--OrderHistory
+        "#,
+    )
+    .unwrap();
+    dot_writer.configure(&ast).unwrap();
+    dot_writer.eval_ast(&ast);
+    dot_writer.eval_legend(&ast);
+    let dot_string = dot_writer.get_dot_string_from_hbs().unwrap();
+    assert!(dot_string.contains("subgraph cluster_legend {"));
+    assert!(dot_string.contains(r#"legend_Command [label="Command", shape=box, fillcolor="steelblue4"];"#));
+    assert!(dot_string.contains(r#"legend_Event [label="Event", shape=box, fillcolor="darkorange3"];"#));
+    assert!(dot_string.contains(r#"legend_ReadModel [label="ReadModel", shape=box, fillcolor="seagreen4"];"#));
+    assert!(!dot_string.contains("legend_Policy"));
+    assert!(dot_string.contains(r#"legend_Arrow_from -> legend_Arrow_to [label="Arrow"];"#));
+    assert!(dot_string.contains(r#"legend_Line_from -> legend_Line_to [label="Line", dir=none];"#));
+    assert!(dot_string.contains("ProductOrdered -> OrderHistory [dir=none, ];"));
+  }
+
+  #[test]
+  fn test_drawio_string() {
+    let mut dot_writer = DotWriter::new();
+    let ast = crate::parsers::parse(
+      r#"
+        t:Ordering
+        !theme dark
+        !display caption
+        c:OrderProduct:"注文する"
+        e:ProductOrdered
+        OrderProduct->ProductOrdered
+        "#
+      .as_bytes(),
+    )
+    .unwrap();
+    dot_writer.configure(&ast).unwrap();
+    dot_writer.eval_ast(&ast);
+    let drawio = dot_writer.get_drawio_string(&ast);
+    assert!(drawio.contains(r#"<diagram id="event-grapher" name="Ordering">"#));
+    assert!(drawio.contains(r##"background="#262626""##));
+    assert!(drawio.contains(
+      r#"value="注文する" style="rounded=0;whiteSpace=wrap;html=0;shadow=1;strokeColor=none;fillColor=#36648B;"#
+    ));
+    assert!(drawio.contains(
+      r#"value="ProductOrdered" style="rounded=0;whiteSpace=wrap;html=0;shadow=1;strokeColor=none;fillColor=#CD6600;"#
+    ));
+    assert!(drawio.contains(r#"<mxGeometry x="40" y="190" width="140" height="90" as="geometry"/>"#));
+    assert!(drawio.contains(r#"source="node-1" target="node-2""#));
+  }
+
+  #[test]
+  fn test_excalidraw_string() {
+    let mut dot_writer = DotWriter::new();
+    let ast = crate::parsers::parse(
+      r#"
+        !theme dark
+        c:OrderProduct:"注文する"
+        e:ProductOrdered
+        OrderProduct->ProductOrdered
+        "#
+      .as_bytes(),
+    )
+    .unwrap();
+    dot_writer.configure(&ast).unwrap();
+    dot_writer.eval_ast(&ast);
+    let board: Json = serde_json::from_str(&dot_writer.get_excalidraw_string(&ast).unwrap()).unwrap();
+    assert_eq!(board["appState"]["viewBackgroundColor"], "#262626");
+    let elements = board["elements"].as_array().unwrap();
+    assert_eq!(elements[0]["backgroundColor"], "#36648B");
+    assert_eq!(elements[2]["backgroundColor"], "#CD6600");
+    assert_eq!(elements[4]["startBinding"]["elementId"], "node-1");
+    assert_eq!(elements[4]["endBinding"]["elementId"], "node-2");
+  }
+
+  #[test]
+  fn test_diff_styles_of_duplicated_edges() {
+    let before = crate::parsers::parse(b"c:OrderProduct\na:Order\nOrderProduct->Order\nOrderProduct->Order\n").unwrap();
+    let after = crate::parsers::parse(b"c:OrderProduct\na:Order\nOrderProduct->Order\n").unwrap();
+    let diff = ModelDiff::new(&before, &after);
+    let combined = diff.combined(&after);
+    let mut dot_writer = DotWriter::new().with_diff(diff);
+    dot_writer.configure(&combined).unwrap();
+    dot_writer.eval_ast(&combined);
+    let dot_string = dot_writer.get_dot_string_from_hbs().unwrap();
+    assert!(dot_string.contains("OrderProduct -> Order [];"));
+    assert_eq!(dot_string.matches("OrderProduct -> Order [color=\"red\"").count(), 1);
+  }
+
+  #[test]
+  fn test_diff_styles() {
+    let before = crate::parsers::parse(b"c:OrderProduct\na:Order\ne:Cancelled\nOrderProduct->Order\n").unwrap();
+    let after = crate::parsers::parse(b"c:OrderProduct\ne:Order\ne:Placed\nOrderProduct->Placed\n").unwrap();
+    let diff = ModelDiff::new(&before, &after);
+    let combined = diff.combined(&after);
+    let mut dot_writer = DotWriter::new().with_diff(diff);
+    dot_writer.configure(&combined).unwrap();
+    dot_writer.eval_ast(&combined);
+    let dot_string = dot_writer.get_dot_string_from_hbs().unwrap();
+    assert!(dot_string.contains(r#"OrderProduct [shape=box, fillcolor="lightblue"]"#));
+    assert!(
+      dot_string.contains(r#"Order [shape=box, fillcolor="orange", color="blue", style="filled,bold", penwidth=3]"#)
+    );
+    assert!(
+      dot_string.contains(r#"Placed [shape=box, fillcolor="orange", color="green3", style="filled,bold", penwidth=2]"#)
+    );
+    assert!(dot_string
+      .contains(r#"Cancelled [shape=box, fillcolor="orange", color="red", style="filled,dashed", penwidth=2]"#));
+    assert!(
+      dot_string.contains(r#"OrderProduct -> Placed [color="green3", fontcolor="green3", style="bold", penwidth=2, ]"#)
+    );
+    assert!(
+      dot_string.contains(r#"OrderProduct -> Order [color="red", fontcolor="red", style="dashed", penwidth=2, ]"#)
+    );
+  }
+
+  #[test]
+  fn test_caption_is_kept_when_names_are_displayed() {
+    let mut dot_writer = DotWriter::new();
+    let ast = crate::parsers::parse(
+      r#"
+        !display name
+        !node_label table
+        !locale ja
+        e:OrderPlaced:"Order placed":ja"注文された"
+        "#
+      .as_bytes(),
+    )
+    .unwrap();
+    dot_writer.configure(&ast).unwrap();
+    dot_writer.eval_ast(&ast);
+    // the label shows the name only, but the viewer still searches the localized caption
+    assert_eq!(dot_writer.nodes[0].label, None);
+    assert_eq!(dot_writer.nodes[0].caption.as_deref(), Some("注文された"));
+    assert!(!dot_writer.nodes[0]
+      .html_label
+      .as_deref()
+      .unwrap()
+      .contains("注文された"));
+  }
+
+  #[test]
+  fn test_table_label() {
+    let mut dot_writer = DotWriter::new();
+    let ast = Ast::Documents(vec![
+      Ast::SettingDef(Setting::new("node_label".to_string(), "table".to_string())),
+      Ast::NameDef(
+        Name::of_policy("NotifyCustomer".to_string(), Caption::new("通知 & <記録>".to_string())).with_attributes(vec![
+          crate::ast::Attribute::new("owner".to_string(), "team \"a\"".to_string()),
+        ]),
+      ),
+    ]);
+    dot_writer.configure(&ast).unwrap();
+    dot_writer.eval_ast(&ast);
+    assert_eq!(
+      dot_writer.nodes[0].html_label.as_deref(),
+      Some(concat!(
+        r#"<TABLE BORDER="0" CELLBORDER="0" CELLSPACING="0"><TR><TD><FONT POINT-SIZE="9">«Policy»</FONT></TD></TR>"#,
+        r#"<TR><TD><B>NotifyCustomer</B></TD></TR><TR><TD>通知 &amp; &lt;記録&gt;</TD></TR><HR/>"#,
+        r#"<TR><TD ALIGN="LEFT">owner: team &quot;a&quot;</TD></TR></TABLE>"#
+      ))
+    );
+  }
+
+  #[test]
+  fn test_table_label_without_attributes() {
+    let mut dot_writer = DotWriter::new();
+    let ast = crate::parsers::parse(
+      br#"
+        !node_label table
+        !hide attributes
+        e:OrderPlaced { owner = "team-a", orderId: OrderId }
+        "#,
+    )
+    .unwrap();
+    dot_writer.configure(&ast).unwrap();
+    dot_writer.eval_ast(&ast);
+    let label = dot_writer.nodes[0].html_label.as_deref().unwrap();
+    assert!(!label.contains("owner"));
+    assert!(label.contains(r#"<HR/><TR><TD ALIGN="LEFT">orderId: OrderId</TD></TR></TABLE>"#));
+  }
+
+  fn import_rendered(source: &str) -> Ast {
+    let ast = crate::parsers::parse(source.as_bytes()).unwrap();
+    let mut dot_writer = DotWriter::new();
+    dot_writer.configure(&ast).unwrap();
+    dot_writer.eval_ast(&ast);
+    crate::import::dot::import(&dot_writer.get_dot_string_from_hbs().unwrap()).unwrap()
+  }
+
+  #[test]
+  fn test_import_rendered_labels() {
+    let expected = crate::parsers::parse(
+      r#"
+      e:OrderPlaced:"x = y & z"
+      r:History
+      OrderPlaced->History:"a < b"
+      "#
+      .as_bytes(),
+    )
+    .unwrap();
+    assert_eq!(
+      import_rendered(
+        r#"
+        !node_label table
+        e:OrderPlaced:"x = y & z" { owner = "team-a", orderId: OrderId } #billing
+        r:History
+        OrderPlaced->History:"a < b"
+        "#
+      ),
+      expected
+    );
+    assert_eq!(
+      import_rendered(
+        r#"
+        e:OrderPlaced:"x = y & z" { orderId: OrderId }
+        r:History { total: Money }
+        OrderPlaced->History:"a < b"
+        "#
+      ),
+      expected
+    );
+    assert_eq!(
+      import_rendered(
+        r#"
+        !display both
+        e:OrderPlaced:"x = y & z"
+        r:History
+        OrderPlaced->History:"a < b"
+        "#
+      ),
+      expected
+    );
+  }
+
   #[test]
   fn it_works3() {
     let mut visitor = DotWriter::new();
     let ast = Ast::Arrow(crate::ast::Arrow {
       from_ref: "Test".to_string(),
       to_ref: "Test".to_string(),
-      caption: None,
+      caption: Caption::default(),
     });
     visitor.eval_ast(&ast);
     assert_eq!(visitor.edges.len(), 1);
@@ -288,24 +1559,30 @@ pub mod tests {
   #[test]
   fn it_works4() {
     env::set_var("RUST_LOG", "debug");
-    env_logger::init();
+    let _ = env_logger::try_init();
     let mut dot_writer = DotWriter::new();
     let ast = Ast::Documents(vec![
-      Ast::TitleDef(Name::of_title("Test".to_string(), None)),
+      Ast::TitleDef(Name::of_title("Test".to_string(), Caption::default())),
       Ast::NameDef(crate::ast::Name {
         name_type: NameType::Event,
         name: "ordered".to_string(),
-        caption: Some("注文された".to_string()),
+        caption: Caption::new("注文された".to_string()),
+        attributes: vec![],
+        fields: vec![],
+        tags: vec![],
       }),
       Ast::NameDef(crate::ast::Name {
         name_type: NameType::Event,
         name: "shipping".to_string(),
-        caption: Some("出荷された".to_string()),
+        caption: Caption::new("出荷された".to_string()),
+        attributes: vec![],
+        fields: vec![],
+        tags: vec![],
       }),
       Ast::Arrow(crate::ast::Arrow {
         from_ref: "ordered".to_string(),
         to_ref: "shipping".to_string(),
-        caption: None, //Some("XYZ".to_string()),
+        caption: Caption::default(), //Some("XYZ".to_string()),
       }),
     ]);
     dot_writer.render(&ast, "target/test").unwrap();
@@ -314,7 +1591,7 @@ pub mod tests {
   #[test]
   fn test() {
     env::set_var("RUST_LOG", "debug");
-    env_logger::init();
+    let _ = env_logger::try_init();
     let mut dot_writer = DotWriter::new();
     let eg = r#"
         t:G:"title"
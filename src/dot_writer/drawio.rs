@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+
+use super::{grid_positions, warn_undrawn_edge, DiffStyle, Edge, EdgeType, Node, NodeType};
+use crate::layout::Cell;
+use crate::settings::{hex_color, Theme};
+
+const MARGIN: usize = 40;
+const WIDTH: usize = 140;
+const HEIGHT: usize = 90;
+const COLUMN_GAP: usize = 40;
+const ROW_GAP: usize = 60;
+
+fn escape_xml(s: &str) -> String {
+  s.replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+    .replace('"', "&quot;")
+    .replace('\n', "&#xa;")
+}
+
+fn sticky_style(node_type: &NodeType) -> &'static str {
+  match node_type {
+    NodeType::User => "shape=note;size=12;",
+    NodeType::Aggregate => "fontStyle=1;",
+    NodeType::HotSpot => "rotation=-5;",
+    NodeType::Command | NodeType::Event | NodeType::Policy | NodeType::ReadModel => "",
+  }
+}
+
+fn stroke(diff_style: Option<&DiffStyle>, color: &str) -> String {
+  match diff_style.map(DiffStyle::stroke) {
+    Some((diff_color, width, dashed)) => format!(
+      "strokeColor={};strokeWidth={};{}",
+      diff_color,
+      width,
+      if dashed { "dashed=1;" } else { "" }
+    ),
+    None => format!("strokeColor={};", color),
+  }
+}
+
+pub fn render_drawio(title: &str, nodes: &[Node], edges: &[Edge], cells: &[Cell], theme: &Theme) -> String {
+  let positions = grid_positions(nodes, cells);
+  let font_color = hex_color(theme.font_color());
+  let line_color = hex_color(theme.line_color());
+  let mut xml = String::new();
+  xml.push_str("<mxfile host=\"event-grapher-rs\">\n");
+  xml.push_str(&format!(
+    "  <diagram id=\"event-grapher\" name=\"{}\">\n",
+    escape_xml(title)
+  ));
+  xml.push_str(&format!(
+    "    <mxGraphModel grid=\"1\" gridSize=\"10\" page=\"0\" background=\"{}\">\n",
+    hex_color(theme.background_color())
+  ));
+  xml.push_str("      <root>\n");
+  xml.push_str("        <mxCell id=\"0\"/>\n");
+  xml.push_str("        <mxCell id=\"1\" parent=\"0\"/>\n");
+
+  let mut ids = HashMap::new();
+  for (index, node) in nodes.iter().enumerate() {
+    let id = format!("node-{}", index + 1);
+    let (row, column) = positions[&node.name];
+    let style = format!(
+      "rounded=0;whiteSpace=wrap;html=0;shadow=1;{}fillColor={};fontColor={};{}{}",
+      stroke(node.diff_style.as_ref(), "none"),
+      hex_color(&node.fill_color),
+      font_color,
+      sticky_style(&node.node_type),
+      if node.dimmed { "opacity=40;" } else { "" }
+    );
+    xml.push_str(&format!(
+      "        <mxCell id=\"{}\" value=\"{}\" style=\"{}\" vertex=\"1\" parent=\"1\">\n",
+      id,
+      escape_xml(node.label.as_deref().unwrap_or(&node.name)),
+      style
+    ));
+    xml.push_str(&format!(
+      "          <mxGeometry x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" as=\"geometry\"/>\n",
+      MARGIN + column * (WIDTH + COLUMN_GAP),
+      MARGIN + row * (HEIGHT + ROW_GAP),
+      WIDTH,
+      HEIGHT
+    ));
+    xml.push_str("        </mxCell>\n");
+    ids.insert(node.name.as_str(), id);
+  }
+
+  for (index, edge) in edges.iter().enumerate() {
+    let (Some(source), Some(target)) = (ids.get(edge.from.as_str()), ids.get(edge.to.as_str())) else {
+      warn_undrawn_edge(edge);
+      continue;
+    };
+    let style = format!(
+      "edgeStyle=orthogonalEdgeStyle;rounded=1;html=0;{}fontColor={};endArrow={};{}",
+      stroke(edge.diff_style.as_ref(), &line_color),
+      font_color,
+      if edge.edge_type == EdgeType::Line {
+        "none"
+      } else {
+        "classic"
+      },
+      if edge.dimmed { "opacity=40;" } else { "" }
+    );
+    xml.push_str(&format!(
+      "        <mxCell id=\"edge-{}\" value=\"{}\" style=\"{}\" edge=\"1\" parent=\"1\" source=\"{}\" target=\"{}\">\n",
+      index + 1,
+      escape_xml(edge.label.as_deref().unwrap_or_default()),
+      style,
+      source,
+      target
+    ));
+    xml.push_str("          <mxGeometry relative=\"1\" as=\"geometry\"/>\n");
+    xml.push_str("        </mxCell>\n");
+  }
+
+  xml.push_str("      </root>\n");
+  xml.push_str("    </mxGraphModel>\n");
+  xml.push_str("  </diagram>\n");
+  xml.push_str("</mxfile>\n");
+  xml
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::diff::DiffStatus;
+  use crate::layout::Lane;
+
+  #[test]
+  fn test_render_drawio() {
+    let nodes = vec![
+      Node::new(
+        "PlaceOrder".to_string(),
+        NodeType::Command,
+        "box".to_string(),
+        "lightblue".to_string(),
+        Some("注文する".to_string()),
+        Vec::new(),
+      ),
+      Node::new(
+        "OrderPlaced".to_string(),
+        NodeType::Event,
+        "box".to_string(),
+        "orange".to_string(),
+        Some("Order \"placed\"\n<v2>".to_string()),
+        Vec::new(),
+      ),
+    ];
+    let edges = vec![
+      Edge::new(
+        EdgeType::Arrow,
+        "PlaceOrder".to_string(),
+        "OrderPlaced".to_string(),
+        Some("1".to_string()),
+      ),
+      Edge::new(EdgeType::Line, "OrderPlaced".to_string(), "Missing".to_string(), None),
+    ];
+    let cells = vec![
+      Cell {
+        name: "PlaceOrder".to_string(),
+        lane: Lane::Commands,
+        column: 0,
+      },
+      Cell {
+        name: "OrderPlaced".to_string(),
+        lane: Lane::Events,
+        column: 1,
+      },
+    ];
+    let xml = render_drawio("Ordering", &nodes, &edges, &cells, &Theme::Light);
+    assert!(xml.starts_with("<mxfile host=\"event-grapher-rs\">\n  <diagram id=\"event-grapher\" name=\"Ordering\">"));
+    assert!(xml.contains(concat!(
+      r#"<mxCell id="node-1" value="注文する" style="rounded=0;whiteSpace=wrap;html=0;shadow=1;strokeColor=none;"#,
+      r#"fillColor=#ADD8E6;fontColor=#000000;" vertex="1" parent="1">"#,
+      "\n",
+      r#"          <mxGeometry x="40" y="40" width="140" height="90" as="geometry"/>"#
+    )));
+    assert!(xml.contains(r#"value="Order &quot;placed&quot;&#xa;&lt;v2&gt;""#));
+    assert!(xml.contains(r#"<mxGeometry x="220" y="190" width="140" height="90" as="geometry"/>"#));
+    assert!(xml.contains(concat!(
+      r#"<mxCell id="edge-1" value="1" style="edgeStyle=orthogonalEdgeStyle;rounded=1;html=0;strokeColor=#000000;"#,
+      r#"fontColor=#000000;endArrow=classic;" edge="1" parent="1" source="node-1" target="node-2">"#
+    )));
+    assert!(!xml.contains("edge-2"));
+
+    let nodes = vec![Node::new(
+      "OrderPlaced".to_string(),
+      NodeType::Event,
+      "box".to_string(),
+      "orange".to_string(),
+      None,
+      Vec::new(),
+    )
+    .with_diff_style(Some(DiffStyle::new(&DiffStatus::Removed, true)))];
+    let edges = vec![Edge::new(
+      EdgeType::Arrow,
+      "OrderPlaced".to_string(),
+      "OrderPlaced".to_string(),
+      None,
+    )
+    .with_diff_style(Some(DiffStyle::new(&DiffStatus::Added, false)))];
+    let xml = render_drawio("Ordering", &nodes, &edges, &cells[1..], &Theme::Light);
+    assert!(xml.contains("shadow=1;strokeColor=#FF0000;strokeWidth=2;dashed=1;fillColor=#FFA500;"));
+    assert!(xml.contains("html=0;strokeColor=#00CD00;strokeWidth=2;fontColor=#000000;endArrow=classic;"));
+    assert!(xml.ends_with("</mxGraphModel>\n  </diagram>\n</mxfile>\n"));
+  }
+}
@@ -0,0 +1,402 @@
+use std::collections::HashMap;
+
+use serde_json::{json, Value as Json};
+
+use super::{grid_positions, warn_undrawn_edge, DiffStyle, Edge, EdgeType, Node};
+use crate::layout::Cell;
+use crate::settings::{hex_color, Theme};
+
+const MARGIN: f64 = 40.0;
+const WIDTH: f64 = 160.0;
+const HEIGHT: f64 = 100.0;
+const COLUMN_GAP: f64 = 60.0;
+const ROW_GAP: f64 = 80.0;
+const FONT_SIZE: f64 = 20.0;
+const LINE_HEIGHT: f64 = 1.25;
+const BINDING_GAP: f64 = 8.0;
+
+// Excalidraw needs a seed and nonce per element; deriving them from the index keeps exports stable.
+fn base(id: &str, element_type: &str, index: usize, x: f64, y: f64, width: f64, height: f64) -> Json {
+  json!({
+    "id": id,
+    "type": element_type,
+    "x": x,
+    "y": y,
+    "width": width,
+    "height": height,
+    "angle": 0,
+    "strokeColor": "#1e1e1e",
+    "backgroundColor": "transparent",
+    "fillStyle": "solid",
+    "strokeWidth": 1,
+    "strokeStyle": "solid",
+    "roughness": 1,
+    "opacity": 100,
+    "groupIds": [],
+    "frameId": null,
+    "roundness": null,
+    "seed": index + 1,
+    "version": 1,
+    "versionNonce": index + 1,
+    "isDeleted": false,
+    "boundElements": [],
+    "updated": 1,
+    "link": null,
+    "locked": false
+  })
+}
+
+struct Label<'a> {
+  id: String,
+  container_id: &'a str,
+  text: &'a str,
+  center: (f64, f64),
+  color: &'a str,
+}
+
+// Text bound to a sticky or an arrow, centred on it.
+fn label(index: usize, label: &Label) -> Json {
+  let lines = label.text.lines().collect::<Vec<_>>();
+  let width = lines.iter().map(|line| line.chars().count()).max().unwrap_or_default() as f64 * FONT_SIZE * 0.6;
+  let height = lines.len().max(1) as f64 * FONT_SIZE * LINE_HEIGHT;
+  let mut element = base(
+    &label.id,
+    "text",
+    index,
+    label.center.0 - width / 2.0,
+    label.center.1 - height / 2.0,
+    width,
+    height,
+  );
+  element["strokeColor"] = json!(label.color);
+  element["text"] = json!(label.text);
+  element["originalText"] = json!(label.text);
+  element["fontSize"] = json!(FONT_SIZE);
+  element["fontFamily"] = json!(1);
+  element["textAlign"] = json!("center");
+  element["verticalAlign"] = json!("middle");
+  element["containerId"] = json!(label.container_id);
+  element["lineHeight"] = json!(LINE_HEIGHT);
+  element["autoResize"] = json!(true);
+  element
+}
+
+fn apply_diff_style(element: &mut Json, diff_style: Option<&DiffStyle>) {
+  if let Some((color, width, dashed)) = diff_style.map(DiffStyle::stroke) {
+    element["strokeColor"] = json!(color);
+    element["strokeWidth"] = json!(width);
+    if dashed {
+      element["strokeStyle"] = json!("dashed");
+    }
+  }
+}
+
+// Connects the facing sides of the two stickies: left/right when they are further apart horizontally,
+// otherwise top/bottom.
+fn endpoints(from: (f64, f64), to: (f64, f64)) -> ((f64, f64), (f64, f64)) {
+  let center = |(x, y): (f64, f64)| (x + WIDTH / 2.0, y + HEIGHT / 2.0);
+  let (from_center, to_center) = (center(from), center(to));
+  let (dx, dy) = (to_center.0 - from_center.0, to_center.1 - from_center.1);
+  if dx.abs() >= dy.abs() {
+    let offset = dx.signum() * (WIDTH / 2.0 + BINDING_GAP);
+    (
+      (from_center.0 + offset, from_center.1),
+      (to_center.0 - offset, to_center.1),
+    )
+  } else {
+    let offset = dy.signum() * (HEIGHT / 2.0 + BINDING_GAP);
+    (
+      (from_center.0, from_center.1 + offset),
+      (to_center.0, to_center.1 - offset),
+    )
+  }
+}
+
+pub fn render_excalidraw(
+  nodes: &[Node],
+  edges: &[Edge],
+  cells: &[Cell],
+  theme: &Theme,
+) -> Result<String, serde_json::Error> {
+  let positions = grid_positions(nodes, cells);
+  let font_color = hex_color(theme.font_color());
+  let line_color = hex_color(theme.line_color());
+  let mut elements = Vec::new();
+  let mut shapes = HashMap::new();
+
+  for (index, node) in nodes.iter().enumerate() {
+    let id = format!("node-{}", index + 1);
+    let (row, column) = positions[&node.name];
+    let origin = (
+      MARGIN + column as f64 * (WIDTH + COLUMN_GAP),
+      MARGIN + row as f64 * (HEIGHT + ROW_GAP),
+    );
+    let mut shape = base(&id, "rectangle", elements.len(), origin.0, origin.1, WIDTH, HEIGHT);
+    shape["backgroundColor"] = json!(hex_color(&node.fill_color));
+    shape["strokeColor"] = json!(line_color);
+    shape["roundness"] = json!({ "type": 3 });
+    if node.dimmed {
+      shape["opacity"] = json!(40);
+    }
+    apply_diff_style(&mut shape, node.diff_style.as_ref());
+    shape["boundElements"] = json!([{ "type": "text", "id": format!("{}-text", id) }]);
+    let text = label(
+      elements.len() + 1,
+      &Label {
+        id: format!("{}-text", id),
+        container_id: &id,
+        text: node.label.as_deref().unwrap_or(&node.name),
+        center: (origin.0 + WIDTH / 2.0, origin.1 + HEIGHT / 2.0),
+        color: &font_color,
+      },
+    );
+    shapes.insert(node.name.as_str(), (elements.len(), id, origin));
+    elements.push(shape);
+    elements.push(text);
+  }
+
+  for (index, edge) in edges.iter().enumerate() {
+    let (Some((from_index, from_id, from)), Some((to_index, to_id, to))) =
+      (shapes.get(edge.from.as_str()), shapes.get(edge.to.as_str()))
+    else {
+      warn_undrawn_edge(edge);
+      continue;
+    };
+    let id = format!("edge-{}", index + 1);
+    let (start, end) = endpoints(*from, *to);
+    let mut arrow = base(
+      &id,
+      "arrow",
+      elements.len(),
+      start.0,
+      start.1,
+      (end.0 - start.0).abs(),
+      (end.1 - start.1).abs(),
+    );
+    arrow["strokeColor"] = json!(line_color);
+    arrow["roundness"] = json!({ "type": 2 });
+    arrow["points"] = json!([[0.0, 0.0], [end.0 - start.0, end.1 - start.1]]);
+    arrow["startBinding"] = json!({ "elementId": from_id, "focus": 0, "gap": BINDING_GAP });
+    arrow["endBinding"] = json!({ "elementId": to_id, "focus": 0, "gap": BINDING_GAP });
+    arrow["startArrowhead"] = Json::Null;
+    arrow["endArrowhead"] = match edge.edge_type {
+      EdgeType::Arrow => json!("arrow"),
+      EdgeType::Line => Json::Null,
+    };
+    if edge.dimmed {
+      arrow["opacity"] = json!(40);
+    }
+    apply_diff_style(&mut arrow, edge.diff_style.as_ref());
+    // the stickies list the arrows bound to them so that the arrows follow when they are moved
+    for shape_index in [*from_index, *to_index] {
+      if let Some(bound) = elements[shape_index]["boundElements"].as_array_mut() {
+        bound.push(json!({ "type": "arrow", "id": id }));
+      }
+    }
+    let text = edge.label.as_deref().filter(|text| !text.is_empty()).map(|text| {
+      arrow["boundElements"] = json!([{ "type": "text", "id": format!("{}-text", id) }]);
+      label(
+        elements.len() + 1,
+        &Label {
+          id: format!("{}-text", id),
+          container_id: &id,
+          text,
+          center: ((start.0 + end.0) / 2.0, (start.1 + end.1) / 2.0),
+          color: &font_color,
+        },
+      )
+    });
+    elements.push(arrow);
+    elements.extend(text);
+  }
+
+  serde_json::to_string_pretty(&json!({
+    "type": "excalidraw",
+    "version": 2,
+    "source": "event-grapher-rs",
+    "elements": elements,
+    "appState": {
+      "viewBackgroundColor": hex_color(theme.background_color()),
+      "theme": match theme {
+        Theme::Light => "light",
+        Theme::Dark => "dark",
+      },
+      "gridSize": null
+    },
+    "files": {}
+  }))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::ast::name_type::NameType;
+  use crate::ast::Ast;
+  use crate::diff::DiffStatus;
+  use crate::dot_writer::NodeType;
+  use crate::import::excalidraw::{import, ColorMapping};
+  use crate::layout::Lane;
+
+  #[test]
+  fn test_render_excalidraw() {
+    let node = |name: &str, node_type: NodeType, fill_color: &str| {
+      Node::new(
+        name.to_string(),
+        node_type,
+        "box".to_string(),
+        fill_color.to_string(),
+        None,
+        Vec::new(),
+      )
+    };
+    let nodes = vec![
+      node("PlaceOrder", NodeType::Command, "lightblue"),
+      node("OrderPlaced", NodeType::Event, "orange"),
+      node("OrderHistory", NodeType::ReadModel, "lightgreen"),
+    ];
+    let edges = vec![
+      Edge::new(
+        EdgeType::Arrow,
+        "PlaceOrder".to_string(),
+        "OrderPlaced".to_string(),
+        Some("1".to_string()),
+      ),
+      Edge::new(
+        EdgeType::Line,
+        "OrderPlaced".to_string(),
+        "OrderHistory".to_string(),
+        None,
+      ),
+      Edge::new(EdgeType::Arrow, "OrderPlaced".to_string(), "Missing".to_string(), None),
+    ];
+    let cells = [
+      ("PlaceOrder", Lane::Commands, 0),
+      ("OrderPlaced", Lane::Events, 0),
+      ("OrderHistory", Lane::Views, 1),
+    ]
+    .into_iter()
+    .map(|(name, lane, column)| Cell {
+      name: name.to_string(),
+      lane,
+      column,
+    })
+    .collect::<Vec<_>>();
+    let board = render_excalidraw(&nodes, &edges, &cells, &Theme::Light).unwrap();
+
+    let value: Json = serde_json::from_str(&board).unwrap();
+    assert_eq!(value["type"], "excalidraw");
+    assert_eq!(value["appState"]["viewBackgroundColor"], "#FFFFFF");
+    let elements = value["elements"].as_array().unwrap();
+    assert_eq!(elements.len(), 9);
+    assert_eq!(elements[0]["type"], "rectangle");
+    assert_eq!(elements[0]["backgroundColor"], "#ADD8E6");
+    assert_eq!(
+      (elements[0]["x"].as_f64(), elements[0]["y"].as_f64()),
+      (Some(40.0), Some(40.0))
+    );
+    assert_eq!(
+      elements[0]["boundElements"],
+      json!([{ "type": "text", "id": "node-1-text" }, { "type": "arrow", "id": "edge-1" }])
+    );
+    assert_eq!(
+      elements[2]["boundElements"],
+      json!([
+        { "type": "text", "id": "node-2-text" },
+        { "type": "arrow", "id": "edge-1" },
+        { "type": "arrow", "id": "edge-2" }
+      ])
+    );
+    assert_eq!(
+      (elements[1]["containerId"].as_str(), elements[1]["text"].as_str()),
+      (Some("node-1"), Some("PlaceOrder"))
+    );
+    assert_eq!(elements[6]["startBinding"]["elementId"], "node-1");
+    assert_eq!(elements[6]["endBinding"]["elementId"], "node-2");
+    assert_eq!(elements[6]["points"], json!([[0.0, 0.0], [0.0, 64.0]]));
+    assert_eq!(elements[6]["endArrowhead"], "arrow");
+    assert_eq!(
+      (elements[7]["containerId"].as_str(), elements[7]["text"].as_str()),
+      (Some("edge-1"), Some("1"))
+    );
+    assert_eq!(elements[8]["points"], json!([[0.0, 0.0], [44.0, 180.0]]));
+    assert_eq!(elements[8]["endArrowhead"], Json::Null);
+    assert!(!board.contains("edge-3"));
+
+    let Ast::Documents(documents) = import(&board, &ColorMapping::default()).unwrap() else {
+      panic!("expected documents");
+    };
+    let names = documents
+      .iter()
+      .filter_map(|document| match document {
+        Ast::NameDef(name) => Some((name.name.as_str(), name.name_type.clone())),
+        _ => None,
+      })
+      .collect::<Vec<_>>();
+    assert_eq!(
+      names,
+      vec![
+        ("PlaceOrder", NameType::Command),
+        ("OrderPlaced", NameType::Event),
+        ("OrderHistory", NameType::ReadModel),
+      ]
+    );
+    assert_eq!(documents.len(), 5);
+  }
+
+  #[test]
+  fn test_render_excalidraw_diff_in_dark_theme() {
+    let node = |name: &str, fill_color: &str, status: DiffStatus| {
+      Node::new(
+        name.to_string(),
+        NodeType::Aggregate,
+        "box".to_string(),
+        fill_color.to_string(),
+        None,
+        Vec::new(),
+      )
+      .with_diff_style(Some(DiffStyle::new(&status, true)))
+    };
+    let nodes = vec![
+      node("Order", "goldenrod4", DiffStatus::Removed),
+      node("Invoice", "goldenrod4", DiffStatus::Added),
+    ];
+    let edges = vec![
+      Edge::new(EdgeType::Arrow, "Order".to_string(), "Invoice".to_string(), None)
+        .with_diff_style(Some(DiffStyle::new(&DiffStatus::Removed, false))),
+    ];
+    let cells = [("Order", 0), ("Invoice", 1)]
+      .into_iter()
+      .map(|(name, column)| Cell {
+        name: name.to_string(),
+        lane: Lane::Aggregates,
+        column,
+      })
+      .collect::<Vec<_>>();
+    let board = render_excalidraw(&nodes, &edges, &cells, &Theme::Dark).unwrap();
+
+    let value: Json = serde_json::from_str(&board).unwrap();
+    let elements = value["elements"].as_array().unwrap();
+    let stroke = |index: usize| {
+      (
+        elements[index]["strokeColor"].as_str(),
+        elements[index]["strokeWidth"].as_u64(),
+        elements[index]["strokeStyle"].as_str(),
+      )
+    };
+    assert_eq!(stroke(0), (Some("#FF0000"), Some(2), Some("dashed")));
+    assert_eq!(stroke(2), (Some("#00CD00"), Some(2), Some("solid")));
+    assert_eq!(stroke(4), (Some("#FF0000"), Some(2), Some("dashed")));
+
+    let Ast::Documents(documents) = import(&board, &ColorMapping::default()).unwrap() else {
+      panic!("expected documents");
+    };
+    let types = documents
+      .iter()
+      .filter_map(|document| match document {
+        Ast::NameDef(name) => Some(name.name_type.clone()),
+        _ => None,
+      })
+      .collect::<Vec<_>>();
+    assert_eq!(types, vec![NameType::Aggregate, NameType::Aggregate]);
+  }
+}
@@ -0,0 +1,73 @@
+pub fn escape_html(s: &str) -> String {
+  s.replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+    .replace('"', "&quot;")
+}
+
+fn inline_to_html(s: &str) -> String {
+  let mut html = String::new();
+  let mut rest = s;
+  while !rest.is_empty() {
+    if let Some(inner) = rest.strip_prefix("**") {
+      if let Some(end) = inner.find("**") {
+        html.push_str(&format!("<B>{}</B>", inline_to_html(&inner[..end])));
+        rest = &inner[end + 2..];
+        continue;
+      }
+    } else if let Some(inner) = rest.strip_prefix('*') {
+      if let Some(end) = inner.find('*') {
+        if end > 0 {
+          html.push_str(&format!("<I>{}</I>", inline_to_html(&inner[..end])));
+          rest = &inner[end + 1..];
+          continue;
+        }
+      }
+    }
+    let c = rest.chars().next().unwrap();
+    html.push_str(&escape_html(&c.to_string()));
+    rest = &rest[c.len_utf8()..];
+  }
+  html
+}
+
+fn line_to_html(line: &str) -> String {
+  let trimmed = line.trim_start();
+  match trimmed.strip_prefix("- ").or_else(|| trimmed.strip_prefix("* ")) {
+    Some(item) => format!("• {}", inline_to_html(item)),
+    None => inline_to_html(line),
+  }
+}
+
+pub fn to_html(text: &str) -> String {
+  text.lines().map(line_to_html).collect::<Vec<_>>().join("<BR/>")
+}
+
+pub fn rich_label(text: &str) -> Option<String> {
+  let html = to_html(text);
+  if html == escape_html(text) {
+    None
+  } else {
+    Some(html)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_to_html() {
+    assert_eq!(
+      to_html("**注文**された\n- *速達*\n- a < b"),
+      "<B>注文</B>された<BR/>• <I>速達</I><BR/>• a &lt; b"
+    );
+  }
+
+  #[test]
+  fn test_rich_label() {
+    assert_eq!(rich_label("plain & simple"), None);
+    assert_eq!(rich_label("1 * 2 = 2"), None);
+    assert_eq!(rich_label("first\nsecond"), Some("first<BR/>second".to_string()));
+  }
+}
@@ -0,0 +1,118 @@
+use handlebars::{to_json, Handlebars};
+use serde_derive::Serialize;
+use serde_json::value::{Map, Value as Json};
+
+use super::{Edge, EdgeType, Node, NodeType};
+
+#[derive(Serialize)]
+struct ViewerNode<'a> {
+  name: &'a str,
+  node_type: &'a NodeType,
+  caption: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct ViewerEdge<'a> {
+  from: &'a str,
+  to: &'a str,
+  directed: bool,
+}
+
+#[derive(Serialize)]
+struct ViewerModel<'a> {
+  nodes: Vec<ViewerNode<'a>>,
+  edges: Vec<ViewerEdge<'a>>,
+  node_types: Vec<&'a NodeType>,
+}
+
+impl<'a> ViewerModel<'a> {
+  fn new(nodes: &'a [Node], edges: &'a [Edge]) -> Self {
+    let mut node_types = Vec::new();
+    for node in nodes {
+      if !node_types.contains(&&node.node_type) {
+        node_types.push(&node.node_type);
+      }
+    }
+    Self {
+      nodes: nodes
+        .iter()
+        .map(|node| ViewerNode {
+          name: &node.name,
+          node_type: &node.node_type,
+          caption: node.caption.as_deref(),
+        })
+        .collect(),
+      edges: edges
+        .iter()
+        .map(|edge| ViewerEdge {
+          from: &edge.from,
+          to: &edge.to,
+          directed: edge.edge_type == EdgeType::Arrow,
+        })
+        .collect(),
+      node_types,
+    }
+  }
+}
+
+pub fn render_html(
+  context: &Map<String, Json>,
+  svg: &str,
+  nodes: &[Node],
+  edges: &[Edge],
+) -> Result<String, Box<dyn std::error::Error>> {
+  let mut handlebars = Handlebars::new();
+  handlebars.register_template_string("viewer", include_str!("../../viewer.hbs"))?;
+  // the model is embedded in a <script> element, so it must not close it
+  let model = serde_json::to_string(&ViewerModel::new(nodes, edges))?.replace("</", "<\\/");
+  let svg = svg.find("<svg").map_or(svg, |start| &svg[start..]);
+  let mut context = context.clone();
+  context.insert("svg".to_string(), to_json(svg));
+  context.insert("model".to_string(), to_json(model));
+  Ok(handlebars.render("viewer", &context)?)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::dot_writer::NodeAttribute;
+
+  #[test]
+  fn test_render_html() {
+    let nodes = vec![
+      Node::new(
+        "ordered".to_string(),
+        NodeType::Event,
+        "box".to_string(),
+        "orange".to_string(),
+        None,
+        Vec::<NodeAttribute>::new(),
+      )
+      .with_caption(Some("</script>".to_string())),
+      Node::new(
+        "history".to_string(),
+        NodeType::ReadModel,
+        "box".to_string(),
+        "lightgreen".to_string(),
+        None,
+        Vec::new(),
+      ),
+    ];
+    let edges = vec![Edge::new(
+      EdgeType::Line,
+      "ordered".to_string(),
+      "history".to_string(),
+      None,
+    )];
+    let svg = r#"<?xml version="1.0"?><svg viewBox="0 0 10 10"><g class="node"><title>ordered</title></g></svg>"#;
+    let html = render_html(&Map::new(), svg, &nodes, &edges).unwrap();
+    assert!(html.contains(r#"<svg viewBox="0 0 10 10"><g class="node"><title>ordered</title></g></svg>"#));
+    assert!(!html.contains("<?xml"));
+    assert!(html.contains(concat!(
+      r#"var model = {"nodes":[{"name":"ordered","node_type":"Event","caption":"<\/script>"},"#,
+      r#"{"name":"history","node_type":"ReadModel","caption":null}],"#,
+      r#""edges":[{"from":"ordered","to":"history","directed":false}],"node_types":["Event","ReadModel"]};"#
+    )));
+    assert!(!html.contains("src=\"http"));
+  }
+}
@@ -0,0 +1,298 @@
+use std::str::FromStr;
+
+use crate::ast::name_type::NameType;
+use crate::ast::{Ast, Caption, Name};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FormatOrder {
+  Source,
+  Grouped,
+}
+
+impl FromStr for FormatOrder {
+  type Err = ();
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "source" => Ok(FormatOrder::Source),
+      "grouped" => Ok(FormatOrder::Grouped),
+      _ => Err(()),
+    }
+  }
+}
+
+// The reverse of the escape sequences accepted by `utf16_string`.
+fn quote(s: &str) -> String {
+  let mut quoted = String::from("\"");
+  for c in s.chars() {
+    match c {
+      '\\' => quoted.push_str("\\\\"),
+      '"' => quoted.push_str("\\\""),
+      '\n' => quoted.push_str("\\n"),
+      '\r' => quoted.push_str("\\r"),
+      '\t' => quoted.push_str("\\t"),
+      c => quoted.push(c),
+    }
+  }
+  quoted.push('"');
+  quoted
+}
+
+fn is_bare_value(s: &str) -> bool {
+  !s.is_empty()
+    && s
+      .chars()
+      .all(|c| !c.is_whitespace() && !matches!(c, ',' | '{' | '}' | '"'))
+}
+
+fn prefix(name_type: &NameType) -> &'static str {
+  match name_type {
+    NameType::Title => "t",
+    NameType::User => "u",
+    NameType::Command => "c",
+    NameType::Event => "e",
+    NameType::Aggregate => "a",
+    NameType::Policy => "p",
+    NameType::ReadModel => "r",
+    NameType::HotSpot => "h",
+  }
+}
+
+// Items are laid out in sections separated by a blank line; captions are aligned within a section.
+fn section(ast: &Ast, order: &FormatOrder) -> usize {
+  match (ast, order) {
+    (Ast::TitleDef(_) | Ast::SettingDef(_) | Ast::Include(_), _) => 0,
+    (Ast::NameDef(_), FormatOrder::Source) => 1,
+    (Ast::NameDef(name), FormatOrder::Grouped) => match name.name_type {
+      NameType::Title | NameType::User => 1,
+      NameType::Command => 2,
+      NameType::Aggregate => 3,
+      NameType::Event => 4,
+      NameType::Policy => 5,
+      NameType::ReadModel => 6,
+      NameType::HotSpot => 7,
+    },
+    _ => 8,
+  }
+}
+
+fn head(ast: &Ast) -> String {
+  match ast {
+    Ast::TitleDef(name) | Ast::NameDef(name) => format!("{}:{}", prefix(&name.name_type), name.name),
+    Ast::Arrow(arrow) => format!("{}->{}", arrow.from_ref, arrow.to_ref),
+    Ast::Line(line) => format!("{}--{}", line.from_ref, line.to_ref),
+    Ast::Include(path) => format!("include {}", quote(path)),
+    Ast::SettingDef(setting) if is_bare_value(&setting.value) => format!("!{} {}", setting.key, setting.value),
+    Ast::SettingDef(setting) => format!("!{} {}", setting.key, quote(&setting.value)),
+    _ => String::new(),
+  }
+}
+
+fn caption_of(ast: &Ast) -> Option<&Caption> {
+  let caption = match ast {
+    Ast::TitleDef(name) | Ast::NameDef(name) => &name.caption,
+    Ast::Arrow(arrow) => &arrow.caption,
+    Ast::Line(line) => &line.caption,
+    _ => return None,
+  };
+  (!caption.is_empty()).then_some(caption)
+}
+
+fn tail(name: &Name) -> String {
+  let mut tail = String::new();
+  for tag in &name.tags {
+    tail.push_str(&format!(" @{}", tag));
+  }
+  let properties = name
+    .attributes
+    .iter()
+    .map(|attribute| {
+      if is_bare_value(&attribute.value) {
+        format!("{}={}", attribute.key, attribute.value)
+      } else {
+        format!("{}={}", attribute.key, quote(&attribute.value))
+      }
+    })
+    .chain(
+      name
+        .fields
+        .iter()
+        .map(|field| format!("{}: {}", field.name, field.field_type)),
+    )
+    .collect::<Vec<_>>();
+  if !properties.is_empty() {
+    tail.push_str(&format!(" {{{}}}", properties.join(", ")));
+  }
+  tail
+}
+
+fn line(ast: &Ast, width: usize) -> String {
+  let head = head(ast);
+  let mut line = head.clone();
+  if let Some(caption) = caption_of(ast) {
+    line.push_str(&" ".repeat(width - head.chars().count()));
+    for (locale, text) in caption.iter() {
+      line.push_str(&format!(":{}{}", locale, quote(text)));
+    }
+  }
+  if let Ast::TitleDef(name) | Ast::NameDef(name) = ast {
+    line.push_str(&tail(name));
+  }
+  line
+}
+
+fn flatten<'a>(ast: &'a Ast, items: &mut Vec<&'a Ast>) {
+  match ast {
+    Ast::Documents(documents) => {
+      for document in documents {
+        flatten(document, items);
+      }
+    }
+    Ast::Empty => {}
+    other => items.push(other),
+  }
+}
+
+pub fn format(ast: &Ast, order: &FormatOrder) -> String {
+  let mut items = Vec::new();
+  flatten(ast, &mut items);
+
+  // comments stay attached to the item that follows them
+  let mut entries: Vec<(Vec<&String>, &Ast)> = Vec::new();
+  let mut comments = Vec::new();
+  for item in items {
+    match item {
+      Ast::Comment(comment) => comments.push(comment),
+      item => entries.push((std::mem::take(&mut comments), item)),
+    }
+  }
+  if *order == FormatOrder::Grouped {
+    entries.sort_by_key(|(_, item)| section(item, order));
+  }
+
+  let mut sections: Vec<Vec<(Vec<&String>, &Ast)>> = Vec::new();
+  for entry in entries {
+    match sections.last_mut() {
+      Some(last) if section(last[0].1, order) == section(entry.1, order) => last.push(entry),
+      _ => sections.push(vec![entry]),
+    }
+  }
+
+  let mut blocks = Vec::new();
+  for section in sections {
+    let width = section
+      .iter()
+      .filter(|(_, item)| caption_of(item).is_some())
+      .map(|(_, item)| head(item).chars().count())
+      .max()
+      .unwrap_or(0);
+    let mut lines = Vec::new();
+    for (comments, item) in section {
+      lines.extend(
+        comments
+          .iter()
+          .map(|comment| format!("# {}", comment).trim_end().to_string()),
+      );
+      lines.push(line(item, width));
+    }
+    blocks.push(lines.join("\n"));
+  }
+  if !comments.is_empty() {
+    blocks.push(
+      comments
+        .iter()
+        .map(|comment| format!("# {}", comment).trim_end().to_string())
+        .collect::<Vec<_>>()
+        .join("\n"),
+    );
+  }
+  let mut formatted = blocks.join("\n\n");
+  formatted.push('\n');
+  formatted
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parsers::parse;
+
+  const SOURCE: &str = r#"
+# order flow
+!direction   TB
+c:OrderProduct:"注文する"   @mvp
+  e:ProductOrdered : ja"注文された" : en"Product \"ordered\""  {orderId: OrderId, owner=team-a}
+a:Order
+OrderProduct->Order
+Order -> ProductOrdered:"1\n2"
+ProductOrdered--Order
+include "payments.eg"
+# end
+"#;
+
+  #[test]
+  fn test_format_in_source_order() {
+    let ast = parse(SOURCE.as_bytes()).unwrap();
+    assert_eq!(
+      format(&ast, &FormatOrder::Source),
+      r#"# order flow
+!direction TB
+
+c:OrderProduct  :"注文する" @mvp
+e:ProductOrdered:en"Product \"ordered\"":ja"注文された" {owner=team-a, orderId: OrderId}
+a:Order
+
+OrderProduct->Order
+Order->ProductOrdered:"1\n2"
+ProductOrdered--Order
+
+include "payments.eg"
+
+# end
+"#
+    );
+  }
+
+  #[test]
+  fn test_format_grouped() {
+    let ast = parse(SOURCE.as_bytes()).unwrap();
+    assert_eq!(
+      format(&ast, &FormatOrder::Grouped),
+      r#"# order flow
+!direction TB
+include "payments.eg"
+
+c:OrderProduct:"注文する" @mvp
+
+a:Order
+
+e:ProductOrdered:en"Product \"ordered\"":ja"注文された" {owner=team-a, orderId: OrderId}
+
+OrderProduct->Order
+Order->ProductOrdered:"1\n2"
+ProductOrdered--Order
+
+# end
+"#
+    );
+  }
+
+  #[test]
+  fn test_format_round_trips_hot_spots() {
+    let source = "e:OutOfStock\nh:OutOfStock?:\"在庫切れ?\" @risk\n";
+    let ast = parse(source.as_bytes()).unwrap();
+    let formatted = format(&ast, &FormatOrder::Grouped);
+    assert_eq!(formatted, "e:OutOfStock\n\nh:OutOfStock?:\"在庫切れ?\" @risk\n");
+    assert_eq!(parse(formatted.as_bytes()).unwrap(), ast);
+  }
+
+  #[test]
+  fn test_format_is_idempotent() {
+    for order in [FormatOrder::Source, FormatOrder::Grouped] {
+      let formatted = format(&parse(SOURCE.as_bytes()).unwrap(), &order);
+      let reformatted = format(&parse(formatted.as_bytes()).unwrap(), &order);
+      assert_eq!(formatted, reformatted);
+    }
+    let formatted = format(&parse(SOURCE.as_bytes()).unwrap(), &FormatOrder::Source);
+    assert_eq!(parse(formatted.as_bytes()).unwrap(), parse(SOURCE.as_bytes()).unwrap());
+  }
+}
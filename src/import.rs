@@ -0,0 +1,77 @@
+use std::fmt;
+use std::fmt::Formatter;
+
+pub mod csv;
+pub mod dot;
+pub mod excalidraw;
+
+use crate::ast::name_type::NameType;
+
+// The element types in the order their theme fill colours are matched. Lightyellow is shared by users
+// and aggregates in the light theme; the first match wins.
+const FILL_ORDER: [NameType; 7] = [
+  NameType::Event,
+  NameType::Command,
+  NameType::Aggregate,
+  NameType::Policy,
+  NameType::ReadModel,
+  NameType::HotSpot,
+  NameType::User,
+];
+
+#[derive(Debug)]
+pub enum ImportError {
+  Json(serde_json::Error),
+  Csv(::csv::Error),
+  Row { row: usize, message: String },
+  Invalid(String),
+}
+
+impl fmt::Display for ImportError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match self {
+      ImportError::Json(error) => write!(f, "invalid JSON: {}", error),
+      ImportError::Csv(error) => write!(f, "invalid CSV: {}", error),
+      ImportError::Row { row, message } => write!(f, "row {}: {}", row, message),
+      ImportError::Invalid(message) => write!(f, "{}", message),
+    }
+  }
+}
+
+impl std::error::Error for ImportError {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      ImportError::Json(error) => Some(error),
+      ImportError::Csv(error) => Some(error),
+      ImportError::Row { .. } | ImportError::Invalid(_) => None,
+    }
+  }
+}
+
+// Turns free text such as "Order placed" into an element name ("OrderPlaced").
+pub fn element_name(text: &str) -> String {
+  text
+    .split(|c: char| !c.is_alphanumeric())
+    .filter(|word| !word.is_empty())
+    .map(|word| {
+      let mut chars = word.chars();
+      match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+        None => String::new(),
+      }
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_element_name() {
+    assert_eq!(element_name("Order placed"), "OrderPlaced");
+    assert_eq!(element_name(" place\norder-now "), "PlaceOrderNow");
+    assert_eq!(element_name("注文された"), "注文された");
+    assert_eq!(element_name("OrderPlaced"), "OrderPlaced");
+  }
+}
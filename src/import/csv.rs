@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+
+use super::{element_name, ImportError};
+use crate::ast::name_type::NameType;
+use crate::ast::{Arrow, Ast, Caption, Name};
+use crate::parsers::is_name;
+use crate::structured::parse_name_type;
+
+// Header names of the columns to read and the type names accepted besides the built-in ones.
+#[derive(Debug, Clone)]
+pub struct CsvMapping {
+  type_column: String,
+  name_column: String,
+  caption_column: String,
+  next_column: String,
+  aliases: HashMap<String, NameType>,
+}
+
+impl Default for CsvMapping {
+  fn default() -> Self {
+    let aliases = [
+      ("ユーザー", NameType::User),
+      ("アクター", NameType::User),
+      ("コマンド", NameType::Command),
+      ("イベント", NameType::Event),
+      ("集約", NameType::Aggregate),
+      ("ポリシー", NameType::Policy),
+      ("リードモデル", NameType::ReadModel),
+      ("ホットスポット", NameType::HotSpot),
+    ];
+    Self {
+      type_column: "type".to_string(),
+      name_column: "name".to_string(),
+      caption_column: "caption".to_string(),
+      next_column: "next".to_string(),
+      aliases: aliases
+        .into_iter()
+        .map(|(alias, name_type)| (alias.to_string(), name_type))
+        .collect(),
+    }
+  }
+}
+
+impl CsvMapping {
+  pub fn with_column(mut self, key: &str, header: String) -> Result<Self, ImportError> {
+    match key {
+      "type" => self.type_column = header,
+      "name" => self.name_column = header,
+      "caption" => self.caption_column = header,
+      "next" => self.next_column = header,
+      _ => return Err(ImportError::Invalid(format!("unknown column: {}", key))),
+    }
+    Ok(self)
+  }
+
+  pub fn with_alias(mut self, alias: &str, name_type: NameType) -> Self {
+    self.aliases.insert(alias.trim().to_lowercase(), name_type);
+    self
+  }
+
+  fn name_type(&self, s: &str) -> Option<NameType> {
+    let s = s.trim();
+    self
+      .aliases
+      .get(&s.to_lowercase())
+      .cloned()
+      .or_else(|| parse_name_type(s))
+  }
+}
+
+struct Row {
+  line: usize,
+  name: Name,
+  next: Vec<String>,
+}
+
+fn column(headers: &::csv::StringRecord, header: &str) -> Option<usize> {
+  headers.iter().position(|h| h.trim().eq_ignore_ascii_case(header))
+}
+
+// One row per sticky; `next` lists the names (or captions) of the stickies that follow it, separated
+// by `;`, `,` or line breaks. Errors carry the line number of the row in the file.
+pub fn import(input: &str, mapping: &CsvMapping) -> Result<Ast, ImportError> {
+  let mut reader = ::csv::ReaderBuilder::new().flexible(true).from_reader(input.as_bytes());
+  let headers = reader.headers().map_err(ImportError::Csv)?.clone();
+  let required =
+    |header: &str| column(&headers, header).ok_or_else(|| ImportError::Invalid(format!("missing column: {}", header)));
+  let type_column = required(&mapping.type_column)?;
+  let name_column = column(&headers, &mapping.name_column);
+  let caption_column = column(&headers, &mapping.caption_column);
+  let next_column = column(&headers, &mapping.next_column);
+  if name_column.is_none() && caption_column.is_none() {
+    return Err(ImportError::Invalid(format!(
+      "missing column: {} or {}",
+      mapping.name_column, mapping.caption_column
+    )));
+  }
+
+  let mut rows = Vec::new();
+  let mut first_rows = HashMap::new();
+  for record in reader.records() {
+    let record = record.map_err(ImportError::Csv)?;
+    let line = record.position().map_or(0, |position| position.line() as usize);
+    let cell = |index: Option<usize>| index.and_then(|i| record.get(i)).unwrap_or("").trim();
+    if record.iter().all(|value| value.trim().is_empty()) {
+      continue;
+    }
+    let type_name = cell(Some(type_column));
+    let name_type = mapping.name_type(type_name).ok_or_else(|| ImportError::Row {
+      row: line,
+      message: format!("unknown type '{}'", type_name),
+    })?;
+    let caption = cell(caption_column);
+    let name = match cell(name_column) {
+      "" => element_name(caption),
+      name => name.to_string(),
+    };
+    if name.is_empty() {
+      return Err(ImportError::Row {
+        row: line,
+        message: "missing name".to_string(),
+      });
+    }
+    if !is_name(&name) {
+      return Err(ImportError::Row {
+        row: line,
+        message: format!("invalid name '{}'", name),
+      });
+    }
+    // a sticky may be listed again, but not with another type
+    let (first_row, first_type) = first_rows.entry(name.clone()).or_insert((line, name_type.clone()));
+    if *first_type != name_type {
+      return Err(ImportError::Row {
+        row: line,
+        message: format!("'{}' is already defined as {} in row {}", name, first_type, first_row),
+      });
+    }
+    let next = cell(next_column)
+      .split([';', ',', '\n'])
+      .map(str::trim)
+      .filter(|target| !target.is_empty())
+      .map(String::from)
+      .collect();
+    // as in the other importers, a caption that only repeats the name is left out
+    let caption = match caption {
+      "" => Caption::default(),
+      caption if caption == name => Caption::default(),
+      caption => Caption::new(caption.to_string()),
+    };
+    rows.push(Row {
+      line,
+      name: Name {
+        name_type,
+        name,
+        caption,
+        attributes: Vec::new(),
+        fields: Vec::new(),
+        tags: Vec::new(),
+      },
+      next,
+    });
+  }
+
+  let mut references = HashMap::new();
+  for row in &rows {
+    if let Some(caption) = row.name.caption.get(Caption::NEUTRAL) {
+      references.entry(caption.to_string()).or_insert(row.name.name.clone());
+    }
+  }
+  for row in &rows {
+    references.insert(row.name.name.clone(), row.name.name.clone());
+  }
+
+  let mut documents = Vec::new();
+  let mut arrows = Vec::new();
+  for row in rows {
+    for target in &row.next {
+      let to = references.get(target).ok_or_else(|| ImportError::Row {
+        row: row.line,
+        message: format!("unknown reference '{}'", target),
+      })?;
+      arrows.push(Ast::Arrow(Arrow::new(
+        row.name.name.clone(),
+        to.clone(),
+        Caption::default(),
+      )));
+    }
+    let declared = documents
+      .iter()
+      .any(|document| matches!(document, Ast::NameDef(name) | Ast::TitleDef(name) if name.name == row.name.name));
+    if !declared {
+      documents.push(match row.name.name_type {
+        NameType::Title => Ast::TitleDef(row.name),
+        _ => Ast::NameDef(row.name),
+      });
+    }
+  }
+  documents.extend(arrows);
+  Ok(Ast::Documents(documents))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parsers::parse;
+
+  #[test]
+  fn test_import() {
+    let input = "type,name,caption,next\n\
+      u,Customer,,PlaceOrder\n\
+      command,PlaceOrder,注文する,注文された\n\
+      \n\
+      イベント,,注文された,\"OrderHistory; Order\"\n\
+      read model,OrderHistory,,\n\
+      集約,Order,,\n";
+    let ast = import(input, &CsvMapping::default()).unwrap();
+    assert_eq!(
+      ast,
+      parse(
+        r#"
+        u:Customer
+        c:PlaceOrder:"注文する"
+        e:注文された
+        r:OrderHistory
+        a:Order
+        Customer->PlaceOrder
+        PlaceOrder->注文された
+        注文された->OrderHistory
+        注文された->Order
+        "#
+        .as_bytes()
+      )
+      .unwrap()
+    );
+  }
+
+  #[test]
+  fn test_import_with_mapping() {
+    let mapping = CsvMapping::default()
+      .with_column("type", "種類".to_string())
+      .unwrap()
+      .with_column("name", "付箋".to_string())
+      .unwrap()
+      .with_alias("Sticky", NameType::Event);
+    let ast = import("種類,付箋\nsticky,OrderPlaced\n", &mapping).unwrap();
+    assert_eq!(ast, parse(b"e:OrderPlaced").unwrap());
+    assert!(CsvMapping::default().with_column("colour", "x".to_string()).is_err());
+  }
+
+  #[test]
+  fn test_import_errors() {
+    let error = import("type,name\ne,A\nsticky,B\n", &CsvMapping::default()).unwrap_err();
+    assert_eq!(error.to_string(), "row 3: unknown type 'sticky'");
+    let error = import("type,name,next\ne,A,\nc,B,Missing\n", &CsvMapping::default()).unwrap_err();
+    assert_eq!(error.to_string(), "row 3: unknown reference 'Missing'");
+    let error = import("type,name\ne,Order: placed\n", &CsvMapping::default()).unwrap_err();
+    assert_eq!(error.to_string(), "row 2: invalid name 'Order: placed'");
+    let error = import("type,name\ne,Placed\nc,Place\nc,Placed\n", &CsvMapping::default()).unwrap_err();
+    assert_eq!(
+      error.to_string(),
+      "row 4: 'Placed' is already defined as Event in row 2"
+    );
+    let error = import("name\nA\n", &CsvMapping::default()).unwrap_err();
+    assert_eq!(error.to_string(), "missing column: type");
+  }
+}
@@ -0,0 +1,620 @@
+use std::collections::HashMap;
+
+use graphviz_rust::dot_structures::{Attribute, EdgeTy, Graph, GraphAttributes, Id, Stmt, Vertex};
+
+use super::{element_name, ImportError, FILL_ORDER};
+use crate::ast::name_type::NameType;
+use crate::ast::{Arrow, Ast, Caption, Line, Name};
+use crate::settings::Theme;
+use crate::structured::parse_name_type;
+
+fn name_type_of_fill(color: &str) -> Option<NameType> {
+  [Theme::Light, Theme::Dark].iter().find_map(|theme| {
+    FILL_ORDER
+      .iter()
+      .find(|name_type| theme.fill_color(name_type).eq_ignore_ascii_case(color))
+      .cloned()
+  })
+}
+
+fn text(id: &Id) -> Option<String> {
+  match id {
+    Id::Plain(s) => Some(s.clone()),
+    Id::Escaped(s) => {
+      let mut text = String::new();
+      let mut chars = s[1..s.len() - 1].chars();
+      while let Some(c) = chars.next() {
+        match c {
+          '\\' => match chars.next() {
+            Some('n' | 'l' | 'r') => text.push('\n'),
+            Some(escaped) => text.push(escaped),
+            None => {}
+          },
+          c => text.push(c),
+        }
+      }
+      Some(text)
+    }
+    Id::Html(_) | Id::Anonymous(_) => None,
+  }
+}
+
+fn attribute(attributes: &[Attribute], key: &str) -> Option<String> {
+  attributes
+    .iter()
+    .rev()
+    .find(|Attribute(k, _)| text(k).as_deref() == Some(key))
+    .and_then(|Attribute(_, value)| text(value))
+}
+
+// Our own template writes the node type as a trailing comment (`A [...]; // Event`), which the DOT
+// parser drops, so those comments are read from the raw text.
+fn commented_types(input: &str) -> HashMap<String, NameType> {
+  let mut types = HashMap::new();
+  for line in input.lines() {
+    let Some((statement, comment)) = line.rsplit_once("//") else {
+      continue;
+    };
+    let Some(name_type) = parse_name_type(comment.trim()) else {
+      continue;
+    };
+    let statement = statement.trim();
+    let head = statement.split('[').next().unwrap_or_default();
+    if head.contains("->") || head.contains("--") {
+      continue;
+    }
+    let id = match statement.strip_prefix('"') {
+      Some(quoted) => quoted.split('"').next().unwrap_or_default(),
+      None => head.trim().trim_end_matches(';').trim(),
+    };
+    if !id.is_empty() {
+      types.insert(id.to_string(), name_type);
+    }
+  }
+  types
+}
+
+fn quote(s: &str) -> String {
+  format!(
+    "\"{}\"",
+    s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+  )
+}
+
+// HTML labels are reduced to their text, with line breaks for `<BR/>`; entities are left for
+// `decode_entities`.
+fn html_text(html: &str) -> String {
+  let mut text = String::new();
+  let mut rest = html;
+  while let Some(start) = rest.find('<') {
+    text.push_str(&rest[..start]);
+    let end = rest[start..].find('>').map_or(rest.len(), |end| start + end + 1);
+    if rest[start..end].to_lowercase().starts_with("<br") {
+      text.push('\n');
+    }
+    rest = &rest[end..];
+  }
+  text.push_str(rest);
+  text
+}
+
+// `render` draws `!node_label table` as rows of «Type», the name, the caption and (after `<HR/>`)
+// attributes and fields, and structured fields as a header row over field rows. Only the caption
+// (or the header) is kept.
+fn html_label_text(html: &str) -> String {
+  let lower = html.to_ascii_lowercase();
+  if !lower.trim_start().starts_with("<table") {
+    return html_text(html);
+  }
+  let end = lower.find("<hr").unwrap_or(html.len());
+  let rows = lower[..end]
+    .match_indices("<tr")
+    .map(|(start, _)| {
+      let row_end = lower[start..end].find("</tr").map_or(end, |row_end| start + row_end);
+      html_text(&html[start..row_end]).trim().to_string()
+    })
+    .collect::<Vec<_>>();
+  match rows.first() {
+    Some(stereotype) if stereotype.starts_with('«') && stereotype.ends_with('»') => {
+      rows.get(2).cloned().unwrap_or_default()
+    }
+    _ => rows.join("\n"),
+  }
+}
+
+fn named_entity(name: &str) -> Option<char> {
+  Some(match name {
+    "amp" => '&',
+    "lt" => '<',
+    "gt" => '>',
+    "quot" => '"',
+    "apos" => '\'',
+    "nbsp" => '\u{a0}',
+    "laquo" => '«',
+    "raquo" => '»',
+    "bull" => '•',
+    "middot" => '·',
+    "ndash" => '–',
+    "mdash" => '—',
+    "hellip" => '…',
+    "copy" => '©',
+    "reg" => '®',
+    _ => return None,
+  })
+}
+
+// Graphviz reads `&amp;`, `&#61;` and `&#x3D;` in quoted labels as well as in HTML labels; unknown
+// entities and a lone `&` are kept as they are.
+fn decode_entities(s: &str) -> String {
+  let mut decoded = String::with_capacity(s.len());
+  let mut rest = s;
+  while let Some(start) = rest.find('&') {
+    decoded.push_str(&rest[..start]);
+    rest = &rest[start..];
+    let entity = rest[1..].find(';').map(|end| &rest[1..end + 1]);
+    let c = entity.and_then(|entity| match entity.strip_prefix('#') {
+      Some(number) => match number.strip_prefix(['x', 'X']) {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => number.parse().ok(),
+      }
+      .and_then(char::from_u32),
+      None => named_entity(entity),
+    });
+    match (entity, c) {
+      (Some(entity), Some(c)) => {
+        decoded.push(c);
+        rest = &rest[entity.len() + 2..];
+      }
+      _ => {
+        decoded.push('&');
+        rest = &rest[1..];
+      }
+    }
+  }
+  decoded.push_str(rest);
+  decoded
+}
+
+fn label(attributes: &[Attribute]) -> Option<String> {
+  attribute(attributes, "label").map(|label| decode_entities(&label))
+}
+
+// The DOT grammar of graphviz_rust only takes ASCII bare ids, needs the `subgraph` keyword and
+// HTML labels ending in `>>`, while Graphviz (and our template) also allow `{ rank = same; ... }`,
+// non-ASCII names such as `注文` and `<a<BR/>b>`.
+fn normalize(input: &str) -> String {
+  let mut normalized = String::with_capacity(input.len());
+  let mut chars = input.chars().peekable();
+  let mut last = None;
+  while let Some(c) = chars.next() {
+    match c {
+      '<' => {
+        let mut html = String::new();
+        let mut depth = 1;
+        for c in chars.by_ref() {
+          match c {
+            '<' => depth += 1,
+            '>' if depth == 1 => break,
+            '>' => depth -= 1,
+            _ => {}
+          }
+          html.push(c);
+        }
+        normalized.push_str(&quote(&html_label_text(&html)));
+      }
+      '"' => {
+        normalized.push(c);
+        while let Some(c) = chars.next() {
+          normalized.push(c);
+          match c {
+            '\\' => normalized.extend(chars.next()),
+            '"' => break,
+            _ => {}
+          }
+        }
+      }
+      // block comments and C preprocessor lines are dropped, as Graphviz does
+      '/' if chars.peek() == Some(&'*') => {
+        chars.next();
+        let mut previous = None;
+        for c in chars.by_ref() {
+          if previous == Some('*') && c == '/' {
+            break;
+          }
+          previous = Some(c);
+        }
+        normalized.push(' ');
+        continue;
+      }
+      '#'
+        if normalized
+          .rsplit('\n')
+          .next()
+          .is_some_and(|line| line.trim().is_empty()) =>
+      {
+        while chars.peek().is_some_and(|c| *c != '\n') {
+          chars.next();
+        }
+        continue;
+      }
+      '/' if chars.peek() == Some(&'/') => {
+        normalized.push(c);
+        while let Some(&c) = chars.peek() {
+          if c == '\n' {
+            break;
+          }
+          normalized.push(c);
+          chars.next();
+        }
+      }
+      '{' if matches!(last, None | Some(';' | '{' | '}' | ']' | '>' | '-')) => normalized.push_str("subgraph {"),
+      c if c.is_alphanumeric() || c == '_' || c == '.' => {
+        let mut token = String::from(c);
+        while let Some(&c) = chars.peek().filter(|c| c.is_alphanumeric() || **c == '_' || **c == '.') {
+          token.push(c);
+          chars.next();
+        }
+        if token.is_ascii() {
+          normalized.push_str(&token);
+        } else {
+          normalized.push_str(&quote(&token));
+        }
+      }
+      c => normalized.push(c),
+    }
+    if !c.is_whitespace() {
+      last = Some(c);
+    }
+  }
+  normalized
+}
+
+#[derive(Default)]
+struct Collector {
+  nodes: Vec<String>,
+  fill_types: HashMap<String, NameType>,
+  labels: HashMap<String, String>,
+  // `(from, to, is_arrow, caption)` by node id
+  edges: Vec<(String, String, bool, Caption)>,
+}
+
+impl Collector {
+  fn node(&mut self, id: &str) {
+    if !self.nodes.iter().any(|node| node == id) {
+      self.nodes.push(id.to_string());
+    }
+  }
+
+  fn fill(&mut self, id: &str, color: Option<String>) {
+    if let Some(name_type) = color.and_then(|color| name_type_of_fill(&color)) {
+      self.fill_types.insert(id.to_string(), name_type);
+    }
+  }
+
+  // `node [...]` defaults apply to the nodes that follow them in the same graph or subgraph,
+  // including nested subgraphs.
+  fn collect(&mut self, stmts: &[Stmt], directed: bool, defaults: &[Attribute]) {
+    let mut defaults = defaults.to_vec();
+    for stmt in stmts {
+      match stmt {
+        Stmt::Node(node) => {
+          let Some(id) = text(&node.id.0) else { continue };
+          self.node(&id);
+          let fill_color = attribute(&node.attributes, "fillcolor").or_else(|| attribute(&defaults, "fillcolor"));
+          self.fill(&id, fill_color);
+          if let Some(label) = label(&node.attributes).filter(|label| !label.is_empty()) {
+            self.labels.insert(id, label);
+          }
+        }
+        Stmt::Edge(edge) => self.edge(&edge.ty, &edge.attributes, directed, &defaults),
+        // the legend drawn by `!show legend` is not part of the model
+        Stmt::Subgraph(subgraph) if text(&subgraph.id).is_some_and(|id| id.starts_with("cluster_legend")) => {}
+        Stmt::Subgraph(subgraph) => self.collect(&subgraph.stmts, directed, &defaults),
+        Stmt::GAttribute(GraphAttributes::Node(attributes)) => defaults.extend(attributes.iter().cloned()),
+        Stmt::Attribute(_) | Stmt::GAttribute(_) => {}
+      }
+    }
+  }
+
+  fn edge(&mut self, ty: &EdgeTy, attributes: &[Attribute], directed: bool, defaults: &[Attribute]) {
+    if attribute(attributes, "style").as_deref() == Some("invis") {
+      return;
+    }
+    let vertices = match ty {
+      EdgeTy::Pair(from, to) => vec![from, to],
+      EdgeTy::Chain(vertices) => vertices.iter().collect(),
+    };
+    let ids = vertices
+      .into_iter()
+      .filter_map(|vertex| match vertex {
+        Vertex::N(node_id) => text(&node_id.0),
+        Vertex::S(_) => {
+          log::warn!("skipping subgraph used as an edge end");
+          None
+        }
+      })
+      .collect::<Vec<_>>();
+    let dir = attribute(attributes, "dir").unwrap_or_else(|| if directed { "forward" } else { "none" }.to_string());
+    let caption = Caption::from(label(attributes).filter(|label| !label.is_empty()));
+    // a node first seen in an edge takes the current defaults
+    for id in &ids {
+      if !self.nodes.contains(id) {
+        self.node(id);
+        self.fill(id, attribute(defaults, "fillcolor"));
+      }
+    }
+    for pair in ids.windows(2) {
+      let (from, to) = (pair[0].clone(), pair[1].clone());
+      match dir.as_str() {
+        "back" => self.edges.push((to, from, true, caption.clone())),
+        "none" => self.edges.push((from, to, false, caption.clone())),
+        "both" => {
+          self.edges.push((from.clone(), to.clone(), true, caption.clone()));
+          self.edges.push((to, from, true, caption.clone()));
+        }
+        _ => self.edges.push((from, to, true, caption.clone())),
+      }
+    }
+  }
+}
+
+fn valid_name(id: &str) -> String {
+  if id.chars().all(|c| c.is_alphanumeric() || c == '_') {
+    id.to_string()
+  } else {
+    element_name(id)
+  }
+}
+
+// Node types come from a `// Type` comment or else the fill colour of either theme; edges become
+// arrows by default, lines with `dir=none` (or in an undirected graph) and two arrows with `dir=both`.
+pub fn import(input: &str) -> Result<Ast, ImportError> {
+  let graph =
+    graphviz_rust::parse(&normalize(input)).map_err(|error| ImportError::Invalid(format!("invalid DOT: {}", error)))?;
+  let (id, stmts, directed) = match &graph {
+    Graph::Graph { id, stmts, .. } => (id, stmts, false),
+    Graph::DiGraph { id, stmts, .. } => (id, stmts, true),
+  };
+  let commented_types = commented_types(input);
+  let mut collector = Collector::default();
+  collector.collect(stmts, directed, &[]);
+
+  let mut documents = Vec::new();
+  if let Some(title) = text(id).filter(|title| !title.is_empty()) {
+    documents.push(Ast::TitleDef(Name::of_title(title, Caption::default())));
+  }
+  let mut names = HashMap::new();
+  for id in &collector.nodes {
+    let name_type = commented_types
+      .get(id)
+      .or_else(|| collector.fill_types.get(id))
+      .cloned()
+      .ok_or_else(|| {
+        ImportError::Invalid(format!(
+          "node {}: cannot infer the element type from fillcolor or a // Type comment",
+          id
+        ))
+      })?;
+    let name = valid_name(id);
+    if name.is_empty() {
+      log::warn!("skipping node {} whose id has no letters or digits", id);
+      continue;
+    }
+    if let Some((first, _)) = names.iter().find(|(_, existing)| **existing == name) {
+      log::warn!(
+        "skipping node {} whose name '{}' is already taken by node {}",
+        id,
+        name,
+        first
+      );
+      continue;
+    }
+    // `render` with `!display both` writes the name as the first line of the label
+    let caption = collector
+      .labels
+      .get(id)
+      .map(|label| label.strip_prefix(&format!("{}\n", id)).unwrap_or(label))
+      .or(Some(id.as_str()))
+      .filter(|label| *label != name)
+      .map(String::from);
+    documents.push(Ast::NameDef(Name {
+      name_type,
+      name: name.clone(),
+      caption: Caption::from(caption),
+      attributes: Vec::new(),
+      fields: Vec::new(),
+      tags: Vec::new(),
+    }));
+    names.insert(id.clone(), name);
+  }
+  for (from, to, is_arrow, caption) in collector.edges {
+    let (Some(from_ref), Some(to_ref)) = (names.get(&from), names.get(&to)) else {
+      log::warn!("skipping edge {} -> {} to a skipped node", from, to);
+      continue;
+    };
+    let (from_ref, to_ref) = (from_ref.clone(), to_ref.clone());
+    documents.push(if is_arrow {
+      Ast::Arrow(Arrow::new(from_ref, to_ref, caption))
+    } else {
+      Ast::Line(Line::new(from_ref, to_ref, caption))
+    });
+  }
+  Ok(Ast::Documents(documents))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::formatter::{format, FormatOrder};
+  use crate::parsers::parse;
+
+  #[test]
+  fn test_import_generated_dot() {
+    let dot = r#"
+digraph Ordering {
+    rankdir = "LR";
+    bgcolor = "white";
+    node [shape=box, style=filled, color="black", fontcolor="black", fillcolor="white"];
+    PlaceOrder [shape=box, fillcolor="lightblue", label="PlaceOrder\n注文する", URL="https://example.com/adr"]; // Command
+    Order [shape=box, fillcolor="lightyellow"]; // Aggregate
+    Customer [shape=box, fillcolor="lightyellow"]; // User
+    OrderPlaced [shape=box, fillcolor="orange"]; // Event
+    注文履歴 [shape=box, fillcolor="lightgreen", label=<注文<BR/>履歴>]; // ReadModel
+    Customer -> PlaceOrder [];
+    PlaceOrder -> Order [label="1"];
+    Order -> OrderPlaced [];
+    OrderPlaced -> Customer [dir=none, ];
+    OrderPlaced -> 注文履歴 [];
+    { rank = same; Customer; }
+    Customer -> PlaceOrder [style=invis];
+    subgraph cluster_legend {
+        label = "Legend";
+        legend_Event [label="Event", shape=box, fillcolor="orange"];
+    }
+}
+"#;
+    assert_eq!(
+      import(dot).unwrap(),
+      parse(
+        r#"
+        t:Ordering
+        c:PlaceOrder:"注文する"
+        a:Order
+        u:Customer
+        e:OrderPlaced
+        r:注文履歴:"注文\n履歴"
+        Customer->PlaceOrder
+        PlaceOrder->Order:"1"
+        Order->OrderPlaced
+        OrderPlaced--Customer
+        OrderPlaced->注文履歴
+        "#
+        .as_bytes()
+      )
+      .unwrap()
+    );
+  }
+
+  #[test]
+  fn test_import_hand_written_dot() {
+    let dot = r#"
+graph {
+    "Order placed" [fillcolor=darkorange3];
+    History [fillcolor=lightgreen];
+    Ship [fillcolor=plum, label="Ship it"];
+    "Order placed" -- History;
+    Ship -- History [dir=forward];
+    History -- Ship [dir=back];
+}
+"#;
+    assert_eq!(
+      import(dot).unwrap(),
+      parse(
+        r#"
+        e:OrderPlaced:"Order placed"
+        r:History
+        p:Ship:"Ship it"
+        OrderPlaced--History
+        Ship->History
+        Ship->History
+        "#
+        .as_bytes()
+      )
+      .unwrap()
+    );
+    assert_eq!(
+      import(
+        r#"
+digraph {
+    node [fillcolor=orange];
+    Placed;
+    subgraph cluster_views {
+        node [fillcolor=lightgreen];
+        History;
+        Placed -> Shipping;
+    }
+    Placed -> Billed;
+}
+"#
+      )
+      .unwrap(),
+      parse(b"e:Placed\nr:History\nr:Shipping\ne:Billed\nPlaced->Shipping\nPlaced->Billed\n").unwrap()
+    );
+    assert_eq!(
+      import("digraph { A -> B; }").unwrap_err().to_string(),
+      "node A: cannot infer the element type from fillcolor or a // Type comment"
+    );
+    assert!(import("digraph {").is_err());
+  }
+
+  #[test]
+  fn test_import_two_way_edges_and_comments() {
+    let dot = r#"
+# 1 "order.gv"
+digraph {
+    /* Shipped [fillcolor=orange];
+       Order -> Shipped; */
+    Order [fillcolor=lightyellow]; /* the aggregate */
+    Placed [fillcolor=orange];
+    Order -> Placed [dir=both, label="1"];
+    # 12 "order.gv"
+}
+"#;
+    assert_eq!(
+      import(dot).unwrap(),
+      parse(b"a:Order\ne:Placed\nOrder->Placed:\"1\"\nPlaced->Order:\"1\"\n").unwrap()
+    );
+  }
+
+  #[test]
+  fn test_import_skips_unusable_names() {
+    let dot = r#"
+digraph {
+    "???" [fillcolor=orange];
+    "Order placed" [fillcolor=orange];
+    OrderPlaced [fillcolor=lightgreen];
+    Ship [fillcolor=plum];
+    Ship -> "???";
+    Ship -> "Order placed";
+    Ship -> OrderPlaced;
+}
+"#;
+    let ast = import(dot).unwrap();
+    assert_eq!(
+      ast,
+      parse(
+        r#"
+        e:OrderPlaced:"Order placed"
+        p:Ship
+        Ship->OrderPlaced
+        "#
+        .as_bytes()
+      )
+      .unwrap()
+    );
+    assert_eq!(parse(format(&ast, &FormatOrder::Source).as_bytes()).unwrap(), ast);
+  }
+
+  #[test]
+  fn test_import_decodes_entities() {
+    let dot = r#"
+digraph {
+    A [fillcolor=orange, label="x &#x3D; y &amp; z"];
+    B [fillcolor=lightgreen, label=<a &#61; b &hellip;<BR/>&lt;c&gt; &unknown; & d>];
+    A -> B [label="&quot;1&quot;"];
+}
+"#;
+    assert_eq!(
+      import(dot).unwrap(),
+      parse(
+        r#"
+        e:A:"x = y & z"
+        r:B:"a = b …\n<c> &unknown; & d"
+        A->B:"\"1\""
+        "#
+        .as_bytes()
+      )
+      .unwrap()
+    );
+  }
+}
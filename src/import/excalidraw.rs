@@ -0,0 +1,326 @@
+use std::collections::HashMap;
+
+use serde_derive::Deserialize;
+
+use super::{element_name, ImportError, FILL_ORDER};
+use crate::ast::name_type::NameType;
+use crate::ast::{Arrow, Ast, Caption, Line, Name};
+use crate::settings::{hex_color, Theme};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Rgb(u8, u8, u8);
+
+impl Rgb {
+  fn parse(color: &str) -> Option<Self> {
+    let hex = color.trim().strip_prefix('#')?;
+    let channel = |s: &str| u8::from_str_radix(s, 16).ok();
+    match hex.len() {
+      6 => Some(Rgb(channel(&hex[0..2])?, channel(&hex[2..4])?, channel(&hex[4..6])?)),
+      3 => {
+        let short = |i: usize| channel(&hex[i..i + 1]).map(|v| v * 17);
+        Some(Rgb(short(0)?, short(1)?, short(2)?))
+      }
+      _ => None,
+    }
+  }
+
+  fn distance(&self, other: &Rgb) -> u32 {
+    [(self.0, other.0), (self.1, other.1), (self.2, other.2)]
+      .iter()
+      .map(|(a, b)| (*a as i32 - *b as i32).pow(2) as u32)
+      .sum()
+  }
+}
+
+// Sticky fill colours and the element types they stand for. A colour that is not listed maps to the
+// type of the nearest listed colour.
+#[derive(Debug, Clone)]
+pub struct ColorMapping {
+  colors: Vec<(Rgb, NameType)>,
+}
+
+impl Default for ColorMapping {
+  // The background colours of the Excalidraw palette, then the fill colours of both themes so that
+  // boards written by `render --format excalidraw` are read back as they were.
+  fn default() -> Self {
+    let colors = [
+      ("#ffd8a8", NameType::Event),
+      ("#ffc078", NameType::Event),
+      ("#ffa94d", NameType::Event),
+      ("#fd7e14", NameType::Event),
+      ("#d0ebff", NameType::Command),
+      ("#a5d8ff", NameType::Command),
+      ("#74c0fc", NameType::Command),
+      ("#4dabf7", NameType::Command),
+      ("#ffec99", NameType::Aggregate),
+      ("#ffe066", NameType::Aggregate),
+      ("#fab005", NameType::Aggregate),
+      ("#d0bfff", NameType::Policy),
+      ("#eebefa", NameType::Policy),
+      ("#e599f7", NameType::Policy),
+      ("#b2f2bb", NameType::ReadModel),
+      ("#8ce99a", NameType::ReadModel),
+      ("#40c057", NameType::ReadModel),
+      ("#ffc9c9", NameType::HotSpot),
+      ("#fcc2d7", NameType::HotSpot),
+      ("#ff8787", NameType::HotSpot),
+      ("#fff9db", NameType::User),
+      ("#fff3bf", NameType::User),
+    ];
+    let theme_colors = [Theme::Light, Theme::Dark].into_iter().flat_map(|theme| {
+      FILL_ORDER
+        .iter()
+        .map(move |name_type| (hex_color(theme.fill_color(name_type)), name_type.clone()))
+    });
+    Self {
+      colors: colors
+        .into_iter()
+        .map(|(color, name_type)| (color.to_string(), name_type))
+        .chain(theme_colors)
+        .map(|(color, name_type)| (Rgb::parse(&color).unwrap(), name_type))
+        .collect(),
+    }
+  }
+}
+
+impl ColorMapping {
+  pub fn with_color(mut self, color: &str, name_type: NameType) -> Result<Self, ImportError> {
+    let rgb = Rgb::parse(color).ok_or_else(|| ImportError::Invalid(format!("invalid colour: {}", color)))?;
+    self.colors.retain(|(c, _)| *c != rgb);
+    self.colors.insert(0, (rgb, name_type));
+    Ok(self)
+  }
+
+  pub fn name_type(&self, color: &str) -> Option<NameType> {
+    let rgb = Rgb::parse(color)?;
+    self
+      .colors
+      .iter()
+      .min_by_key(|(c, _)| c.distance(&rgb))
+      .map(|(_, name_type)| name_type.clone())
+  }
+}
+
+#[derive(Debug, Deserialize)]
+struct Binding {
+  #[serde(rename = "elementId")]
+  element_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BoardElement {
+  id: String,
+  #[serde(rename = "type")]
+  element_type: String,
+  #[serde(default)]
+  x: f64,
+  #[serde(default)]
+  y: f64,
+  #[serde(default)]
+  is_deleted: bool,
+  #[serde(default)]
+  background_color: String,
+  text: Option<String>,
+  container_id: Option<String>,
+  start_binding: Option<Binding>,
+  end_binding: Option<Binding>,
+  start_arrowhead: Option<String>,
+  end_arrowhead: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Board {
+  elements: Vec<BoardElement>,
+}
+
+const SHAPES: [&str; 3] = ["rectangle", "diamond", "ellipse"];
+const CONNECTORS: [&str; 2] = ["arrow", "line"];
+
+fn caption(text: &str, name: &str) -> Caption {
+  if text == name {
+    Caption::default()
+  } else {
+    Caption::new(text.to_string())
+  }
+}
+
+// Shapes become elements (left to right, then top to bottom) and connectors bound to two of them
+// become arrows, or lines when they have no arrow head. Text inside a shape or connector is its label.
+pub fn import(input: &str, mapping: &ColorMapping) -> Result<Ast, ImportError> {
+  let board: Board = serde_json::from_str(input).map_err(ImportError::Json)?;
+  let elements = board.elements.iter().filter(|e| !e.is_deleted).collect::<Vec<_>>();
+  let labels = elements
+    .iter()
+    .filter(|e| e.element_type == "text")
+    .filter_map(|e| Some((e.container_id.as_deref()?, e.text.as_deref()?.trim())))
+    .collect::<HashMap<_, _>>();
+
+  let mut shapes = elements
+    .iter()
+    .filter(|e| SHAPES.contains(&e.element_type.as_str()))
+    .collect::<Vec<_>>();
+  shapes.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+
+  let mut names = HashMap::new();
+  let mut types = HashMap::new();
+  let mut documents = Vec::new();
+  for shape in shapes {
+    let Some(name_type) = mapping.name_type(&shape.background_color) else {
+      log::warn!(
+        "skipping shape {} with unmapped colour {}",
+        shape.id,
+        shape.background_color
+      );
+      continue;
+    };
+    let Some(text) = labels.get(shape.id.as_str()).filter(|text| !text.is_empty()) else {
+      log::warn!("skipping shape {} without text", shape.id);
+      continue;
+    };
+    let name = element_name(text);
+    if name.is_empty() {
+      log::warn!(
+        "skipping shape {} whose text '{}' has no letters or digits",
+        shape.id,
+        text
+      );
+      continue;
+    }
+    // the same text on another sticky refers to the element defined first
+    if let Some(first_type) = types.get(&name) {
+      if *first_type != name_type {
+        log::warn!(
+          "shape {} is a {} but '{}' is already a {}; keeping the first",
+          shape.id,
+          name_type,
+          name,
+          first_type
+        );
+      }
+    } else {
+      types.insert(name.clone(), name_type.clone());
+      let element = Name {
+        name_type,
+        name: name.clone(),
+        caption: caption(text, &name),
+        attributes: Vec::new(),
+        fields: Vec::new(),
+        tags: Vec::new(),
+      };
+      documents.push(match element.name_type {
+        NameType::Title => Ast::TitleDef(element),
+        _ => Ast::NameDef(element),
+      });
+    }
+    names.insert(shape.id.as_str(), name);
+  }
+
+  for connector in elements
+    .iter()
+    .filter(|e| CONNECTORS.contains(&e.element_type.as_str()))
+  {
+    let bound = |binding: &Option<Binding>| {
+      binding
+        .as_ref()
+        .and_then(|binding| names.get(binding.element_id.as_str()))
+        .cloned()
+    };
+    let (Some(from), Some(to)) = (bound(&connector.start_binding), bound(&connector.end_binding)) else {
+      log::warn!("skipping connector {} that is not bound to two stickies", connector.id);
+      continue;
+    };
+    let caption = labels
+      .get(connector.id.as_str())
+      .map(|text| Caption::new(text.to_string()))
+      .unwrap_or_default();
+    documents.push(match (&connector.start_arrowhead, &connector.end_arrowhead) {
+      (None, None) => Ast::Line(Line::new(from, to, caption)),
+      (Some(_), None) => Ast::Arrow(Arrow::new(to, from, caption)),
+      _ => Ast::Arrow(Arrow::new(from, to, caption)),
+    });
+  }
+  Ok(Ast::Documents(documents))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parsers::parse;
+
+  const BOARD: &str = r##"{
+    "type": "excalidraw",
+    "version": 2,
+    "elements": [
+      {"id": "e1", "type": "rectangle", "x": 300, "y": 0, "backgroundColor": "#ffc078"},
+      {"id": "t1", "type": "text", "x": 310, "y": 10, "text": "Order placed", "containerId": "e1"},
+      {"id": "c1", "type": "rectangle", "x": 100, "y": 0, "backgroundColor": "#a5d8ff"},
+      {"id": "t2", "type": "text", "x": 110, "y": 10, "text": "PlaceOrder", "containerId": "c1"},
+      {"id": "r1", "type": "rectangle", "x": 500, "y": 0, "backgroundColor": "#b2f3bc"},
+      {"id": "t3", "type": "text", "x": 510, "y": 10, "text": "注文履歴", "containerId": "r1"},
+      {"id": "x1", "type": "rectangle", "x": 700, "y": 0, "backgroundColor": "#ffc078", "isDeleted": true},
+      {"id": "t4", "type": "text", "x": 710, "y": 10, "text": "Gone", "containerId": "x1"},
+      {"id": "a1", "type": "arrow", "x": 0, "y": 0, "startBinding": {"elementId": "c1"},
+       "endBinding": {"elementId": "e1"}, "startArrowhead": null, "endArrowhead": "arrow"},
+      {"id": "t5", "type": "text", "x": 0, "y": 0, "text": "1", "containerId": "a1"},
+      {"id": "a2", "type": "line", "x": 0, "y": 0, "startBinding": {"elementId": "e1"},
+       "endBinding": {"elementId": "r1"}, "startArrowhead": null, "endArrowhead": null},
+      {"id": "a3", "type": "arrow", "x": 0, "y": 0, "startBinding": {"elementId": "c1"},
+       "endBinding": null, "startArrowhead": null, "endArrowhead": "arrow"},
+      {"id": "n1", "type": "text", "x": 0, "y": 400, "text": "free note"}
+    ],
+    "appState": {}
+  }"##;
+
+  #[test]
+  fn test_import() {
+    let ast = import(BOARD, &ColorMapping::default()).unwrap();
+    assert_eq!(
+      ast,
+      parse(
+        r#"
+        c:PlaceOrder
+        e:OrderPlaced:"Order placed"
+        r:注文履歴
+        PlaceOrder->OrderPlaced:"1"
+        OrderPlaced--注文履歴
+        "#
+        .as_bytes()
+      )
+      .unwrap()
+    );
+  }
+
+  #[test]
+  fn test_import_skips_nameless_shapes_and_keeps_the_first_type() {
+    let board = r##"{
+      "elements": [
+        {"id": "e1", "type": "rectangle", "x": 0, "y": 0, "backgroundColor": "#ffc078"},
+        {"id": "t1", "type": "text", "text": "Order placed", "containerId": "e1"},
+        {"id": "q1", "type": "rectangle", "x": 200, "y": 0, "backgroundColor": "#ffc9c9"},
+        {"id": "t2", "type": "text", "text": "???", "containerId": "q1"},
+        {"id": "e2", "type": "rectangle", "x": 400, "y": 0, "backgroundColor": "#a5d8ff"},
+        {"id": "t3", "type": "text", "text": "Order placed", "containerId": "e2"},
+        {"id": "a1", "type": "arrow", "startBinding": {"elementId": "e1"}, "endBinding": {"elementId": "q1"},
+         "endArrowhead": "arrow"},
+        {"id": "a2", "type": "arrow", "startBinding": {"elementId": "e2"}, "endBinding": {"elementId": "e1"},
+         "endArrowhead": "arrow"}
+      ]
+    }"##;
+    assert_eq!(
+      import(board, &ColorMapping::default()).unwrap(),
+      parse(b"e:OrderPlaced:\"Order placed\"\nOrderPlaced->OrderPlaced\n").unwrap()
+    );
+  }
+
+  #[test]
+  fn test_import_with_custom_colors() {
+    let mapping = ColorMapping::default()
+      .with_color("#ffc078", NameType::HotSpot)
+      .unwrap();
+    let ast = import(BOARD, &mapping).unwrap();
+    let Ast::Documents(documents) = ast else { panic!() };
+    assert!(matches!(&documents[1], Ast::NameDef(name) if name.name_type == NameType::HotSpot));
+    assert!(ColorMapping::default().with_color("orange", NameType::Event).is_err());
+  }
+}
@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+
+use crate::ast::name_type::NameType;
+use crate::ast::Ast;
+use crate::numbering::arrows_in_flow_order;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Lane {
+  Actors,
+  Commands,
+  Events,
+  Aggregates,
+  Views,
+}
+
+impl Lane {
+  pub fn all() -> [Lane; 5] {
+    [
+      Lane::Actors,
+      Lane::Commands,
+      Lane::Events,
+      Lane::Aggregates,
+      Lane::Views,
+    ]
+  }
+
+  pub fn of(name_type: &NameType) -> Option<Lane> {
+    match name_type {
+      NameType::User => Some(Lane::Actors),
+      NameType::Command | NameType::Policy => Some(Lane::Commands),
+      NameType::Event => Some(Lane::Events),
+      NameType::Aggregate => Some(Lane::Aggregates),
+      NameType::ReadModel | NameType::HotSpot => Some(Lane::Views),
+      NameType::Title => None,
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Timeline {
+  pub lanes: Vec<(Lane, Vec<String>)>,
+}
+
+impl Timeline {
+  #[cfg(test)]
+  pub fn lane(&self, lane: &Lane) -> &[String] {
+    self
+      .lanes
+      .iter()
+      .find(|(l, _)| l == lane)
+      .map(|(_, names)| names.as_slice())
+      .unwrap_or_default()
+  }
+}
+
+fn collect_names<'a>(ast: &'a Ast, names: &mut Vec<(&'a str, &'a NameType)>) {
+  match ast {
+    Ast::NameDef(name) => names.push((&name.name, &name.name_type)),
+    Ast::Documents(documents) => {
+      for document in documents {
+        collect_names(document, names);
+      }
+    }
+    _ => {}
+  }
+}
+
+// Elements are ordered by the first step (in flow order) that touches them; elements that are not
+// connected by any arrow keep their source order after the connected ones.
+fn chronological_names(ast: &Ast) -> Vec<(&str, &NameType)> {
+  let mut chronology = HashMap::new();
+  for arrow in arrows_in_flow_order(ast) {
+    for name in [&arrow.from_ref, &arrow.to_ref] {
+      let position = chronology.len();
+      chronology.entry(name.as_str()).or_insert(position);
+    }
+  }
+  let mut names = Vec::new();
+  collect_names(ast, &mut names);
+  names.sort_by_key(|(name, _)| chronology.get(name).copied().unwrap_or(usize::MAX));
+  names
+}
+
+pub fn timeline(ast: &Ast) -> Timeline {
+  let names = chronological_names(ast);
+  let lanes = Lane::all()
+    .into_iter()
+    .map(|lane| {
+      let members = names
+        .iter()
+        .filter(|(_, name_type)| Lane::of(name_type).as_ref() == Some(&lane))
+        .map(|(name, _)| name.to_string())
+        .collect();
+      (lane, members)
+    })
+    .collect();
+  Timeline { lanes }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cell {
+  pub name: String,
+  pub lane: Lane,
+  pub column: usize,
+}
+
+// Places every element in its lane at a column that never goes back in time: an element shares the
+// column of the one before it unless its lane is already taken there.
+pub fn grid(ast: &Ast) -> Vec<Cell> {
+  let mut next_free = HashMap::new();
+  let mut column = 0;
+  let mut cells = Vec::new();
+  for (name, name_type) in chronological_names(ast) {
+    let Some(lane) = Lane::of(name_type) else {
+      continue;
+    };
+    let free = next_free.entry(lane.clone()).or_insert(0);
+    column = column.max(*free);
+    *free = column + 1;
+    cells.push(Cell {
+      name: name.to_string(),
+      lane,
+      column,
+    });
+  }
+  cells
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parsers::parse;
+
+  #[test]
+  fn test_timeline() {
+    let ast = parse(
+      br#"
+        r:OrderHistory
+        e:OrderValidated
+        e:ProductOrdered
+        e:OrderCancelled
+        c:ValidateOrder
+        c:OrderProduct
+        p:ValidateOrderPolicy
+        a:Order
+        u:Customer
+        Customer->OrderProduct
+        OrderProduct->Order
+        Order->ProductOrdered
+        ProductOrdered->ValidateOrderPolicy
+        ValidateOrderPolicy->ValidateOrder
+        ValidateOrder->Order
+        Order->OrderValidated
+        OrderValidated->OrderHistory
+        "#,
+    )
+    .unwrap();
+    let timeline = timeline(&ast);
+    assert_eq!(timeline.lane(&Lane::Actors), ["Customer"]);
+    assert_eq!(
+      timeline.lane(&Lane::Commands),
+      ["OrderProduct", "ValidateOrderPolicy", "ValidateOrder"]
+    );
+    assert_eq!(
+      timeline.lane(&Lane::Events),
+      ["ProductOrdered", "OrderValidated", "OrderCancelled"]
+    );
+    assert_eq!(timeline.lane(&Lane::Aggregates), ["Order"]);
+    assert_eq!(timeline.lane(&Lane::Views), ["OrderHistory"]);
+  }
+
+  #[test]
+  fn test_grid() {
+    let ast = parse(
+      br#"
+        e:OrderCancelled
+        u:Customer
+        c:OrderProduct
+        a:Order
+        e:ProductOrdered
+        c:ShipOrder
+        e:OrderShipped
+        Customer->OrderProduct
+        OrderProduct->Order
+        Order->ProductOrdered
+        ProductOrdered->ShipOrder
+        ShipOrder->OrderShipped
+        "#,
+    )
+    .unwrap();
+    let cells = grid(&ast)
+      .into_iter()
+      .map(|cell| (cell.name, cell.lane, cell.column))
+      .collect::<Vec<_>>();
+    assert_eq!(
+      cells,
+      [
+        ("Customer".to_string(), Lane::Actors, 0),
+        ("OrderProduct".to_string(), Lane::Commands, 0),
+        ("Order".to_string(), Lane::Aggregates, 0),
+        ("ProductOrdered".to_string(), Lane::Events, 0),
+        ("ShipOrder".to_string(), Lane::Commands, 1),
+        ("OrderShipped".to_string(), Lane::Events, 1),
+        ("OrderCancelled".to_string(), Lane::Events, 2),
+      ]
+    );
+  }
+}
@@ -1,6 +1,17 @@
-mod ast;
-mod dot_writer;
+pub mod ast;
+pub mod diff;
+pub mod dot_writer;
+pub mod formatter;
+pub mod import;
+mod layout;
+pub mod loader;
+pub mod model;
+mod numbering;
 mod parsers;
+mod settings;
+pub mod slice;
+pub mod structured;
+mod tag_expr;
 
 pub fn add(left: usize, right: usize) -> usize {
   left + right
@@ -0,0 +1,402 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fmt::Formatter;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::ast::Ast;
+use crate::parsers;
+use crate::structured::{self, StructuredFormat};
+
+#[derive(Debug)]
+pub enum LoadError {
+  Io {
+    path: PathBuf,
+    source: std::io::Error,
+  },
+  Parse {
+    path: PathBuf,
+    message: String,
+  },
+  Cycle {
+    chain: Vec<PathBuf>,
+  },
+  Duplicate {
+    name: String,
+    path: PathBuf,
+    first: PathBuf,
+  },
+  UnknownReference {
+    name: String,
+    path: PathBuf,
+  },
+}
+
+impl fmt::Display for LoadError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match self {
+      LoadError::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+      LoadError::Parse { path, message } => write!(f, "{}: {}", path.display(), message),
+      LoadError::Cycle { chain } => {
+        let chain = chain
+          .iter()
+          .map(|path| path.display().to_string())
+          .collect::<Vec<_>>()
+          .join(" -> ");
+        write!(f, "include cycle detected: {}", chain)
+      }
+      LoadError::Duplicate { name, path, first } => write!(
+        f,
+        "{}: element '{}' is already defined in {}",
+        path.display(),
+        name,
+        first.display()
+      ),
+      LoadError::UnknownReference { name, path } => write!(f, "{}: unknown reference '{}'", path.display(), name),
+    }
+  }
+}
+
+impl std::error::Error for LoadError {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      LoadError::Io { source, .. } => Some(source),
+      _ => None,
+    }
+  }
+}
+
+// References to elements that no loaded file defines are drawn as plain nodes, so they are only warned about.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Ast, LoadError> {
+  let (ast, origins) = load_with_origins(path)?;
+  for error in origins.unknown_references() {
+    log::warn!("{}", error);
+  }
+  Ok(ast)
+}
+
+pub fn load_with_origins<P: AsRef<Path>>(path: P) -> Result<(Ast, Origins), LoadError> {
+  let mut loader = Loader::default();
+  let ast = loader.load_file(path.as_ref())?;
+  Ok((ast, loader.origins))
+}
+
+// The file each element and edge of a merged model was defined in.
+#[derive(Debug, Default)]
+pub struct Origins {
+  elements: HashMap<String, PathBuf>,
+  edges: Vec<(String, String, PathBuf)>,
+}
+
+impl Origins {
+  pub fn element(&self, name: &str) -> Option<&Path> {
+    self.elements.get(name).map(PathBuf::as_path)
+  }
+
+  // Edge ends that are not defined in any loaded file, reported against the file of the edge.
+  pub fn unknown_references(&self) -> Vec<LoadError> {
+    let mut errors = Vec::new();
+    for (from, to, path) in &self.edges {
+      for name in [from, to] {
+        if !self.elements.contains_key(name) {
+          errors.push(LoadError::UnknownReference {
+            name: name.clone(),
+            path: path.clone(),
+          });
+        }
+      }
+    }
+    errors
+  }
+}
+
+pub fn structured_format(path: &Path) -> Option<StructuredFormat> {
+  path
+    .extension()
+    .and_then(|extension| StructuredFormat::from_extension(&extension.to_string_lossy()))
+}
+
+// `.json`, `.yaml`/`.yml` and `.toml` files are read with the structured schema, anything else as DSL.
+pub fn parse_source(path: &Path, input: &[u8]) -> Result<Ast, LoadError> {
+  let parse_error = |message: String| LoadError::Parse {
+    path: path.to_path_buf(),
+    message,
+  };
+  match structured_format(path) {
+    Some(format) => {
+      let input = std::str::from_utf8(input).map_err(|error| parse_error(error.to_string()))?;
+      structured::parse(&format, input).map_err(parse_error)
+    }
+    None => parsers::parse(input).map_err(|error| parse_error(error.to_string())),
+  }
+}
+
+// Files on the include stack detect cycles; a file reached twice through different includes (a diamond)
+// is merged only once.
+#[derive(Default)]
+struct Loader {
+  stack: Vec<PathBuf>,
+  loaded: HashSet<PathBuf>,
+  origins: Origins,
+}
+
+impl Loader {
+  fn load_file(&mut self, path: &Path) -> Result<Ast, LoadError> {
+    let canonical_path = fs::canonicalize(path).map_err(|source| LoadError::Io {
+      path: path.to_path_buf(),
+      source,
+    })?;
+    if self.stack.contains(&canonical_path) {
+      let mut chain = self.stack.clone();
+      chain.push(canonical_path);
+      return Err(LoadError::Cycle { chain });
+    }
+    if !self.loaded.insert(canonical_path.clone()) {
+      return Ok(Ast::Empty);
+    }
+    let input = fs::read(&canonical_path).map_err(|source| LoadError::Io {
+      path: path.to_path_buf(),
+      source,
+    })?;
+    let ast = parse_source(path, &input)?;
+    self.stack.push(canonical_path);
+    let result = self.resolve_includes(ast, path);
+    self.stack.pop();
+    result
+  }
+
+  fn resolve_includes(&mut self, ast: Ast, path: &Path) -> Result<Ast, LoadError> {
+    match ast {
+      Ast::Include(include_path) => {
+        let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+        self.load_file(&base_dir.join(include_path))
+      }
+      Ast::NameDef(name) => {
+        match self.origins.elements.get(&name.name) {
+          Some(first) if first != path => {
+            return Err(LoadError::Duplicate {
+              name: name.name,
+              path: path.to_path_buf(),
+              first: first.clone(),
+            })
+          }
+          Some(_) => {}
+          None => {
+            self.origins.elements.insert(name.name.clone(), path.to_path_buf());
+          }
+        }
+        Ok(Ast::NameDef(name))
+      }
+      Ast::Arrow(arrow) => {
+        let origin = (arrow.from_ref.clone(), arrow.to_ref.clone(), path.to_path_buf());
+        self.origins.edges.push(origin);
+        Ok(Ast::Arrow(arrow))
+      }
+      Ast::Line(line) => {
+        let origin = (line.from_ref.clone(), line.to_ref.clone(), path.to_path_buf());
+        self.origins.edges.push(origin);
+        Ok(Ast::Line(line))
+      }
+      Ast::Documents(documents) => {
+        let documents = documents
+          .into_iter()
+          .map(|document| self.resolve_includes(document, path))
+          .collect::<Result<Vec<_>, _>>()?;
+        Ok(Ast::Documents(documents))
+      }
+      other => Ok(other),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::ast::{Arrow, Caption, Name};
+
+  fn write_files(dir: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = Path::new("target").join("loader_tests").join(dir);
+    for (name, content) in files {
+      let path = dir.join(name);
+      fs::create_dir_all(path.parent().unwrap()).unwrap();
+      fs::write(path, content).unwrap();
+    }
+    dir
+  }
+
+  #[test]
+  fn test_load_with_include() {
+    let dir = write_files(
+      "include",
+      &[
+        ("main.eg", "include \"sub/payments.eg\"\nc:Pay\nPay->PaymentProcessed\n"),
+        ("sub/payments.eg", "e:PaymentProcessed\n"),
+      ],
+    );
+    let ast = load(dir.join("main.eg")).unwrap();
+    assert_eq!(
+      ast,
+      Ast::Documents(vec![
+        Ast::Documents(vec![Ast::NameDef(Name::of_event(
+          "PaymentProcessed".to_string(),
+          Caption::default()
+        ))]),
+        Ast::NameDef(Name::of_command("Pay".to_string(), Caption::default())),
+        Ast::Arrow(Arrow::new(
+          "Pay".to_string(),
+          "PaymentProcessed".to_string(),
+          Caption::default()
+        )),
+      ])
+    );
+  }
+
+  #[test]
+  fn test_load_structured_file() {
+    let dir = write_files(
+      "structured",
+      &[
+        (
+          "main.yaml",
+          "include: [payments.toml]\nelements:\n  - {type: c, name: Pay}\nrelationships:\n  - {from: Pay, to: PaymentProcessed}\n",
+        ),
+        ("payments.toml", "[[elements]]\ntype = \"event\"\nname = \"PaymentProcessed\"\n"),
+        ("broken.json", "{\"elements\": [{\"type\": \"x\", \"name\": \"A\"}]}"),
+      ],
+    );
+    assert_eq!(
+      load(dir.join("main.yaml")).unwrap(),
+      Ast::Documents(vec![
+        Ast::Documents(vec![Ast::NameDef(Name::of_event(
+          "PaymentProcessed".to_string(),
+          Caption::default()
+        ))]),
+        Ast::NameDef(Name::of_command("Pay".to_string(), Caption::default())),
+        Ast::Arrow(Arrow::new(
+          "Pay".to_string(),
+          "PaymentProcessed".to_string(),
+          Caption::default()
+        )),
+      ])
+    );
+    let error = load(dir.join("broken.json")).unwrap_err();
+    assert!(matches!(error, LoadError::Parse { .. }));
+    assert!(error
+      .to_string()
+      .ends_with("broken.json: elements[0]: unknown element type 'x'"));
+  }
+
+  #[test]
+  fn test_load_detects_cycle() {
+    let dir = write_files(
+      "cycle",
+      &[("a.eg", "include \"b.eg\"\n"), ("b.eg", "include \"a.eg\"\n")],
+    );
+    match load(dir.join("a.eg")) {
+      Err(LoadError::Cycle { chain }) => assert_eq!(chain.len(), 3),
+      other => panic!("unexpected result: {:?}", other),
+    }
+  }
+
+  #[test]
+  fn test_load_merges_diamond_includes_once() {
+    let dir = write_files(
+      "diamond",
+      &[
+        ("main.eg", "include \"orders.eg\"\ninclude \"payments.eg\"\n"),
+        ("orders.eg", "include \"shared.eg\"\nc:Order\n"),
+        ("payments.eg", "include \"shared.eg\"\nc:Pay\n"),
+        ("shared.eg", "u:Customer\n"),
+      ],
+    );
+    assert_eq!(
+      load(dir.join("main.eg")).unwrap(),
+      Ast::Documents(vec![
+        Ast::Documents(vec![
+          Ast::Documents(vec![Ast::NameDef(Name::of_user(
+            "Customer".to_string(),
+            Caption::default()
+          ))]),
+          Ast::NameDef(Name::of_command("Order".to_string(), Caption::default())),
+        ]),
+        Ast::Documents(vec![
+          Ast::Empty,
+          Ast::NameDef(Name::of_command("Pay".to_string(), Caption::default()))
+        ]),
+      ])
+    );
+  }
+
+  #[test]
+  fn test_load_reports_duplicate_with_origin() {
+    let dir = write_files(
+      "duplicate",
+      &[
+        ("main.eg", "include \"payments.eg\"\nc:Pay\n"),
+        ("payments.eg", "c:Pay\nc:Pay\n"),
+      ],
+    );
+    match load(dir.join("main.eg")) {
+      Err(LoadError::Duplicate { name, path, first }) => {
+        assert_eq!(name, "Pay");
+        assert_eq!(path, dir.join("main.eg"));
+        assert_eq!(first, dir.join("payments.eg"));
+      }
+      other => panic!("unexpected result: {:?}", other),
+    }
+  }
+
+  // Without requiring the end of input, parsing would stop at the first bad line and silently drop
+  // everything after it.
+  #[test]
+  fn test_load_reports_unparsed_rest_of_included_file() {
+    let dir = write_files(
+      "unparsed",
+      &[
+        ("main.eg", "include \"payments.eg\"\n"),
+        ("payments.eg", "c:Pay\n=> broken\ne:PaymentProcessed\n"),
+      ],
+    );
+    match load(dir.join("main.eg")) {
+      Err(LoadError::Parse { path, .. }) => assert_eq!(path, dir.join("payments.eg")),
+      other => panic!("unexpected result: {:?}", other),
+    }
+  }
+
+  #[test]
+  fn test_unknown_reference_names_the_included_file() {
+    let dir = write_files(
+      "unknown_reference",
+      &[
+        ("main.eg", "include \"payments.eg\"\nc:Pay\nPay->PaymentProcessed\n"),
+        ("payments.eg", "e:PaymentProcessed\nPaymentProcessed->Refunded\n"),
+      ],
+    );
+    let (_, origins) = load_with_origins(dir.join("main.eg")).unwrap();
+    assert_eq!(
+      origins.element("PaymentProcessed"),
+      Some(dir.join("payments.eg").as_path())
+    );
+    assert_eq!(origins.element("Pay"), Some(dir.join("main.eg").as_path()));
+    assert_eq!(
+      origins
+        .unknown_references()
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>(),
+      vec![format!(
+        "{}: unknown reference 'Refunded'",
+        dir.join("payments.eg").display()
+      )]
+    );
+  }
+
+  #[test]
+  fn test_load_reports_missing_file() {
+    let dir = write_files("missing", &[("main.eg", "include \"nowhere.eg\"\n")]);
+    match load(dir.join("main.eg")) {
+      Err(LoadError::Io { path, .. }) => assert_eq!(path, dir.join("nowhere.eg")),
+      other => panic!("unexpected result: {:?}", other),
+    }
+  }
+}
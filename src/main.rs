@@ -1,3 +1,288 @@
-fn main() {
-  println!("Hello, world!");
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use argopt::{cmd_group, subcmd};
+
+use event_grapher_rs::ast::Setting;
+use event_grapher_rs::diff::ModelDiff;
+use event_grapher_rs::dot_writer::{DotWriter, OutputFormat};
+use event_grapher_rs::formatter::{self, FormatOrder};
+use event_grapher_rs::import::csv::{self, CsvMapping};
+use event_grapher_rs::import::dot;
+use event_grapher_rs::import::excalidraw::{self, ColorMapping};
+use event_grapher_rs::loader;
+use event_grapher_rs::model::Model;
+use event_grapher_rs::slice::{slice, SliceDirection};
+use event_grapher_rs::structured;
+
+fn parse_setting(s: &str) -> Result<Setting, Box<dyn std::error::Error>> {
+  match s.split_once('=') {
+    Some((key, value)) => Ok(Setting::new(key.trim().to_string(), value.trim().to_string())),
+    None => Err(format!("expected KEY=VALUE: {}", s).into()),
+  }
 }
+
+fn parse_format(s: &str) -> Result<OutputFormat, Box<dyn std::error::Error>> {
+  OutputFormat::from_str(s).map_err(|_| format!("invalid format: {}", s).into())
+}
+
+fn output_file_name(input: &Path, output: Option<String>) -> String {
+  output.unwrap_or_else(|| input.with_extension("").to_string_lossy().to_string())
+}
+
+/// Render an event storming file as DOT and SVG or HTML
+#[subcmd]
+fn render(
+  /// Input file
+  input: PathBuf,
+  /// Output file name without extension (defaults to the input file name)
+  #[opt(short, long)]
+  output: Option<String>,
+  /// Output format (svg, html, drawio, excalidraw)
+  #[opt(long, default_value = "svg")]
+  format: String,
+  /// Layout direction (LR, RL, TB, BT)
+  #[opt(long)]
+  direction: Option<String>,
+  /// Color theme (light, dark)
+  #[opt(long)]
+  theme: Option<String>,
+  /// Edge routing (none, line, polyline, curved, ortho, spline)
+  #[opt(long)]
+  splines: Option<String>,
+  /// Labels to show on nodes and edges (name, caption, both)
+  #[opt(long)]
+  display: Option<String>,
+  /// Caption locales in order of preference (e.g. "en,ja")
+  #[opt(long)]
+  locale: Option<String>,
+  /// Only render elements whose tags match the expression (e.g. "mvp & !phase2")
+  #[opt(long)]
+  tags: Option<String>,
+  /// How to treat elements not matching --tags (hide, grey)
+  #[opt(long)]
+  tag_mode: Option<String>,
+  /// Layout mode (default, timeline)
+  #[opt(long)]
+  layout: Option<String>,
+  /// Node label mode (plain, table)
+  #[opt(long)]
+  node_label: Option<String>,
+  /// Number the edges as steps (off, flow, source)
+  #[opt(long)]
+  numbering: Option<String>,
+  /// Only render the elements reachable from this element
+  #[opt(long)]
+  focus: Option<String>,
+  /// Direction to follow from --focus (upstream, downstream, both)
+  #[opt(long, default_value = "both")]
+  focus_direction: String,
+  /// Maximum number of edges to follow from --focus
+  #[opt(long)]
+  depth: Option<usize>,
+  /// Link pattern for nodes (e.g. "https://wiki/{type}/{name}")
+  #[opt(long)]
+  url: Option<String>,
+  /// Where to show the title caption (none, top, bottom)
+  #[opt(long)]
+  title: Option<String>,
+  /// Add a legend of the element types and edge kinds in use
+  #[opt(long)]
+  legend: bool,
+  /// Override any in-file setting (KEY=VALUE)
+  #[opt(short, long = "set")]
+  set: Vec<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+  let mut overrides = Vec::new();
+  for (key, value) in [
+    ("direction", direction),
+    ("theme", theme),
+    ("splines", splines),
+    ("display", display),
+    ("locale", locale),
+    ("tags", tags),
+    ("tag_mode", tag_mode),
+    ("layout", layout),
+    ("node_label", node_label),
+    ("numbering", numbering),
+    ("url", url),
+    ("title", title),
+  ] {
+    if let Some(value) = value {
+      overrides.push(Setting::new(key.to_string(), value));
+    }
+  }
+  if legend {
+    overrides.push(Setting::new("show".to_string(), "legend".to_string()));
+  }
+  for s in &set {
+    overrides.push(parse_setting(s)?);
+  }
+  let mut ast = loader::load(&input)?;
+  if let Some(focus) = focus {
+    let direction = SliceDirection::from_str(&focus_direction)
+      .map_err(|_| format!("invalid focus direction: {}", focus_direction))?;
+    ast = slice(&ast, &focus, direction, depth)?;
+  }
+  let mut dot_writer = DotWriter::new()
+    .with_overrides(overrides)
+    .with_format(parse_format(&format)?);
+  dot_writer.render(&ast, &output_file_name(&input, output))
+}
+
+/// Compare two event storming files, print the changes and render them as one diagram
+#[subcmd]
+fn diff(
+  /// Old version
+  before: PathBuf,
+  /// New version
+  after: PathBuf,
+  /// Output file name without extension (defaults to the new file name with a "-diff" suffix)
+  #[opt(short, long)]
+  output: Option<String>,
+  /// Output format (svg, html, drawio, excalidraw)
+  #[opt(long, default_value = "svg")]
+  format: String,
+  /// Print the changes as JSON instead of rendering a diagram
+  #[opt(long)]
+  json: bool,
+  /// Override any in-file setting (KEY=VALUE)
+  #[opt(short, long = "set")]
+  set: Vec<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+  let overrides = set.iter().map(|s| parse_setting(s)).collect::<Result<Vec<_>, _>>()?;
+  let before = loader::load(&before)?;
+  let after_ast = loader::load(&after)?;
+  let model_diff = ModelDiff::new(&before, &after_ast);
+  if json {
+    println!("{}", model_diff.to_json()?);
+    return Ok(());
+  }
+  print!("{}", model_diff);
+  let combined = model_diff.combined(&after_ast);
+  let output = output.unwrap_or_else(|| format!("{}-diff", output_file_name(&after, None)));
+  let mut dot_writer = DotWriter::new()
+    .with_overrides(overrides)
+    .with_format(parse_format(&format)?)
+    .with_diff(model_diff);
+  dot_writer.render(&combined, &output)
+}
+
+/// Rewrite an event storming file in the canonical format
+#[subcmd]
+fn fmt(
+  /// Input file
+  input: PathBuf,
+  /// Report files that are not formatted instead of rewriting them
+  #[opt(long)]
+  check: bool,
+  /// Element order (source, grouped)
+  #[opt(long, default_value = "source")]
+  order: String,
+) -> Result<(), Box<dyn std::error::Error>> {
+  let order = FormatOrder::from_str(&order).map_err(|_| format!("invalid order: {}", order))?;
+  if loader::structured_format(&input).is_some() {
+    return Err(format!("{} is not an event storming source", input.display()).into());
+  }
+  let source = std::fs::read(&input)?;
+  let formatted = formatter::format(&loader::parse_source(&input, &source)?, &order);
+  if formatted.as_bytes() == source.as_slice() {
+    return Ok(());
+  }
+  if check {
+    return Err(format!("{} is not formatted", input.display()).into());
+  }
+  std::fs::write(&input, formatted)?;
+  Ok(())
+}
+
+/// Print the parsed model as versioned JSON or YAML
+#[subcmd]
+fn dump(
+  /// Input file
+  input: PathBuf,
+  /// Output format (json, yaml)
+  #[opt(long, default_value = "json")]
+  format: String,
+  /// Write to this file instead of standard output
+  #[opt(short, long)]
+  output: Option<PathBuf>,
+) -> Result<(), Box<dyn std::error::Error>> {
+  let model = Model::new(loader::load(&input)?);
+  let dumped = match format.to_lowercase().as_str() {
+    "json" => model.to_json()? + "\n",
+    "yaml" | "yml" => model.to_yaml()?,
+    _ => return Err(format!("invalid format: {}", format).into()),
+  };
+  match output {
+    Some(output) => std::fs::write(output, dumped)?,
+    None => print!("{}", dumped),
+  }
+  Ok(())
+}
+
+fn parse_color_mapping(mapping: ColorMapping, s: &str) -> Result<ColorMapping, Box<dyn std::error::Error>> {
+  let (color, name_type) = s
+    .split_once('=')
+    .ok_or_else(|| format!("expected COLOUR=TYPE: {}", s))?;
+  let name_type =
+    structured::parse_name_type(name_type).ok_or_else(|| format!("unknown element type: {}", name_type))?;
+  Ok(mapping.with_color(color, name_type)?)
+}
+
+fn parse_csv_mapping(column: &[String], alias: &[String]) -> Result<CsvMapping, Box<dyn std::error::Error>> {
+  let mut mapping = CsvMapping::default();
+  for s in column {
+    let (key, header) = s.split_once('=').ok_or_else(|| format!("expected KEY=HEADER: {}", s))?;
+    mapping = mapping.with_column(key.trim(), header.trim().to_string())?;
+  }
+  for s in alias {
+    let (alias, name_type) = s.split_once('=').ok_or_else(|| format!("expected ALIAS=TYPE: {}", s))?;
+    let name_type =
+      structured::parse_name_type(name_type).ok_or_else(|| format!("unknown element type: {}", name_type))?;
+    mapping = mapping.with_alias(alias, name_type);
+  }
+  Ok(mapping)
+}
+
+/// Convert a whiteboard export (.excalidraw), a sticky list (.csv) or a Graphviz file (.dot) into an
+/// event storming source
+#[subcmd]
+fn import(
+  /// Input file
+  input: PathBuf,
+  /// Output file (defaults to the input file name with the .eg extension)
+  #[opt(short, long)]
+  output: Option<PathBuf>,
+  /// Map a sticky fill colour to an element type (COLOUR=TYPE, e.g. "#ffc078=event")
+  #[opt(long = "color")]
+  color: Vec<String>,
+  /// CSV header of a column (KEY=HEADER with KEY one of type, name, caption, next)
+  #[opt(long = "column")]
+  column: Vec<String>,
+  /// Additional CSV type name (ALIAS=TYPE, e.g. "付箋=event")
+  #[opt(long = "alias")]
+  alias: Vec<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+  let source = std::fs::read_to_string(&input)?;
+  let extension = input.extension().map(|e| e.to_string_lossy().to_lowercase());
+  let ast = match extension.as_deref() {
+    Some("excalidraw") => {
+      let mapping = color
+        .iter()
+        .try_fold(ColorMapping::default(), |m, s| parse_color_mapping(m, s))?;
+      excalidraw::import(&source, &mapping)?
+    }
+    Some("csv") => csv::import(&source, &parse_csv_mapping(&column, &alias)?)
+      .map_err(|error| format!("{}: {}", input.display(), error))?,
+    Some("dot" | "gv") => dot::import(&source)?,
+    _ => return Err(format!("unsupported import format: {}", input.display()).into()),
+  };
+  let output = output.unwrap_or_else(|| input.with_extension("eg"));
+  std::fs::write(output, formatter::format(&ast, &FormatOrder::Source))?;
+  Ok(())
+}
+
+#[cmd_group(commands = [render, diff, fmt, dump, import])]
+#[opt(author, version, about, long_about = None)]
+fn main() -> Result<(), Box<dyn std::error::Error>> {}
@@ -0,0 +1,155 @@
+use std::fmt;
+use std::fmt::Formatter;
+
+use serde_derive::{Deserialize, Serialize};
+use serde_norway::with::singleton_map_recursive;
+
+use crate::ast::Ast;
+
+// Bump when the serialized shape of the AST changes incompatibly.
+pub const MODEL_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Model {
+  pub version: u32,
+  pub ast: Ast,
+}
+
+impl Model {
+  pub fn new(ast: Ast) -> Self {
+    Self {
+      version: MODEL_VERSION,
+      ast,
+    }
+  }
+
+  fn checked(self) -> Result<Self, ModelError> {
+    if self.version == MODEL_VERSION {
+      Ok(self)
+    } else {
+      Err(ModelError::UnsupportedVersion(self.version))
+    }
+  }
+
+  pub fn to_json(&self) -> Result<String, ModelError> {
+    serde_json::to_string_pretty(self).map_err(ModelError::Json)
+  }
+
+  // YAML uses the same single-key maps as JSON for enum variants instead of YAML tags.
+  pub fn to_yaml(&self) -> Result<String, ModelError> {
+    let mut yaml = Vec::new();
+    singleton_map_recursive::serialize(self, &mut serde_norway::Serializer::new(&mut yaml))
+      .map_err(ModelError::Yaml)?;
+    Ok(String::from_utf8_lossy(&yaml).into_owned())
+  }
+
+  pub fn from_json(s: &str) -> Result<Self, ModelError> {
+    serde_json::from_str::<Self>(s).map_err(ModelError::Json)?.checked()
+  }
+
+  pub fn from_yaml(s: &str) -> Result<Self, ModelError> {
+    let model: Self =
+      singleton_map_recursive::deserialize(serde_norway::Deserializer::from_str(s)).map_err(ModelError::Yaml)?;
+    model.checked()
+  }
+}
+
+#[derive(Debug)]
+pub enum ModelError {
+  Json(serde_json::Error),
+  Yaml(serde_norway::Error),
+  UnsupportedVersion(u32),
+}
+
+impl fmt::Display for ModelError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match self {
+      ModelError::Json(error) => write!(f, "invalid JSON model: {}", error),
+      ModelError::Yaml(error) => write!(f, "invalid YAML model: {}", error),
+      ModelError::UnsupportedVersion(version) => {
+        write!(f, "unsupported model version {} (expected {})", version, MODEL_VERSION)
+      }
+    }
+  }
+}
+
+impl std::error::Error for ModelError {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      ModelError::Json(error) => Some(error),
+      ModelError::Yaml(error) => Some(error),
+      ModelError::UnsupportedVersion(_) => None,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parsers::parse;
+
+  const SOURCE: &str = r#"
+    # order flow
+    !direction TB
+    c:OrderProduct:ja"注文する":en"Order product" @mvp
+    e:ProductOrdered {orderId: OrderId, lines: [OrderLine], owner=team-a}
+    OrderProduct->ProductOrdered:"1"
+    ProductOrdered--OrderProduct
+    include "payments.eg"
+  "#;
+
+  #[test]
+  fn test_to_json() {
+    let ast = parse(
+      br#"c:OrderProduct:"Order" @mvp
+      OrderProduct->ProductOrdered"#,
+    )
+    .unwrap();
+    let value: serde_json::Value = serde_json::from_str(&Model::new(ast).to_json().unwrap()).unwrap();
+    assert_eq!(
+      value,
+      serde_json::json!({
+        "version": 1,
+        "ast": {"documents": [
+          {"name_def": {"type": "Command", "name": "OrderProduct", "caption": {"": "Order"}, "tags": ["mvp"]}},
+          {"arrow": {"from": "OrderProduct", "to": "ProductOrdered"}}
+        ]}
+      })
+    );
+  }
+
+  #[test]
+  fn test_round_trip() {
+    let model = Model::new(parse(SOURCE.as_bytes()).unwrap());
+    assert_eq!(Model::from_json(&model.to_json().unwrap()).unwrap(), model);
+    assert_eq!(Model::from_yaml(&model.to_yaml().unwrap()).unwrap(), model);
+  }
+
+  #[test]
+  fn test_unsupported_version() {
+    let result = Model::from_json(r#"{"version": 99, "ast": "empty"}"#);
+    assert!(matches!(result, Err(ModelError::UnsupportedVersion(99))));
+  }
+
+  #[test]
+  fn test_from_yaml() {
+    let model = Model::from_yaml(
+      r#"
+version: 1
+ast:
+  documents:
+    - name_def: {type: Event, name: ProductOrdered, fields: [{name: lines, field_type: {list: {simple: OrderLine}}}]}
+    - line: {from: ProductOrdered, to: OrderHistory, caption: {en: shows}}
+"#,
+    )
+    .unwrap();
+    assert_eq!(
+      model.ast,
+      parse(
+        br#"e:ProductOrdered {lines: [OrderLine]}
+        ProductOrdered--OrderHistory:en"shows""#
+      )
+      .unwrap()
+    );
+  }
+}
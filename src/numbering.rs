@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+
+use crate::ast::name_type::NameType;
+use crate::ast::{Arrow, Ast, Caption};
+use crate::settings::Numbering;
+
+fn collect_arrows<'a>(ast: &'a Ast, arrows: &mut Vec<&'a Arrow>, name_types: &mut HashMap<&'a str, &'a NameType>) {
+  match ast {
+    Ast::NameDef(name) => {
+      name_types.insert(&name.name, &name.name_type);
+    }
+    Ast::Arrow(arrow) => arrows.push(arrow),
+    Ast::Documents(documents) => {
+      for document in documents {
+        collect_arrows(document, arrows, name_types);
+      }
+    }
+    _ => {}
+  }
+}
+
+// An arrow leaving a node continues the latest arrow that entered the node before it in the source,
+// so that e.g. `Order->OrderValidated` follows `ValidateOrder->Order` rather than every other command.
+fn predecessor(arrows: &[&Arrow], index: usize) -> Option<usize> {
+  let incoming = arrows
+    .iter()
+    .enumerate()
+    .filter(|(i, arrow)| *i != index && arrow.to_ref == arrows[index].from_ref)
+    .map(|(i, _)| i)
+    .collect::<Vec<_>>();
+  incoming
+    .iter()
+    .rev()
+    .find(|i| **i < index)
+    .or_else(|| incoming.first())
+    .copied()
+}
+
+fn flow_order(arrows: &[&Arrow], name_types: &HashMap<&str, &NameType>) -> Vec<usize> {
+  let predecessors = (0..arrows.len())
+    .map(|index| predecessor(arrows, index))
+    .collect::<Vec<_>>();
+  let is_initial = |index: &usize| {
+    matches!(
+      name_types.get(arrows[*index].from_ref.as_str()),
+      Some(NameType::User) | Some(NameType::Command)
+    )
+  };
+  let roots = (0..arrows.len()).filter(|index| predecessors[*index].is_none());
+  let (initial_roots, other_roots): (Vec<_>, Vec<_>) = roots.partition(is_initial);
+
+  let mut order = Vec::new();
+  let mut visited = vec![false; arrows.len()];
+  let starts = initial_roots
+    .into_iter()
+    .chain(other_roots)
+    .chain(0..arrows.len())
+    .collect::<Vec<_>>();
+  for start in starts {
+    let mut stack = vec![start];
+    while let Some(index) = stack.pop() {
+      if visited[index] {
+        continue;
+      }
+      visited[index] = true;
+      order.push(index);
+      let successors = (0..arrows.len()).filter(|i| predecessors[*i] == Some(index) && !visited[*i]);
+      stack.extend(successors.collect::<Vec<_>>().into_iter().rev());
+    }
+  }
+  order
+}
+
+pub fn arrows_in_flow_order(ast: &Ast) -> Vec<&Arrow> {
+  let mut arrows = Vec::new();
+  let mut name_types = HashMap::new();
+  collect_arrows(ast, &mut arrows, &mut name_types);
+  flow_order(&arrows, &name_types)
+    .into_iter()
+    .map(|index| arrows[index])
+    .collect()
+}
+
+fn numbered_caption(step: usize, caption: &Caption) -> Caption {
+  if caption.is_empty() {
+    Caption::new(step.to_string())
+  } else {
+    caption.map(|text| format!("{}: {}", step, text))
+  }
+}
+
+fn rewrite(ast: &Ast, steps: &[usize], index: &mut usize) -> Ast {
+  match ast {
+    Ast::Arrow(arrow) => {
+      let step = steps[*index];
+      *index += 1;
+      Ast::Arrow(Arrow::new(
+        arrow.from_ref.clone(),
+        arrow.to_ref.clone(),
+        numbered_caption(step, &arrow.caption),
+      ))
+    }
+    Ast::Documents(documents) => Ast::Documents(
+      documents
+        .iter()
+        .map(|document| rewrite(document, steps, index))
+        .collect(),
+    ),
+    other => other.clone(),
+  }
+}
+
+pub fn number_edges(ast: &Ast, numbering: &Numbering) -> Ast {
+  let mut arrows = Vec::new();
+  let mut name_types = HashMap::new();
+  collect_arrows(ast, &mut arrows, &mut name_types);
+  let order = match numbering {
+    Numbering::Off => return ast.clone(),
+    Numbering::Source => (0..arrows.len()).collect::<Vec<_>>(),
+    Numbering::Flow => flow_order(&arrows, &name_types),
+  };
+  let mut steps = vec![0; arrows.len()];
+  for (step, index) in order.into_iter().enumerate() {
+    steps[index] = step + 1;
+  }
+  rewrite(ast, &steps, &mut 0)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parsers::parse;
+
+  fn captions_of(ast: &Ast) -> Vec<String> {
+    match ast {
+      Ast::Arrow(arrow) => vec![format!(
+        "{}->{}={}",
+        arrow.from_ref,
+        arrow.to_ref,
+        arrow.caption.resolve(&[]).unwrap_or_default()
+      )],
+      Ast::Documents(documents) => documents.iter().flat_map(captions_of).collect(),
+      _ => vec![],
+    }
+  }
+
+  #[test]
+  fn test_number_edges_in_flow_order() {
+    let ast = parse(
+      br#"
+        c:ValidateOrder
+        c:OrderProduct
+        a:Order
+        e:ProductOrdered
+        e:OrderValidated
+        p:ValidateOrderPolicy
+        OrderProduct->Order
+        Order->ProductOrdered
+        ValidateOrder->Order
+        Order->OrderValidated
+        ProductOrdered->ValidateOrderPolicy:"when ordered"
+        ValidateOrderPolicy->ValidateOrder
+        "#,
+    )
+    .unwrap();
+    let ast = number_edges(&ast, &Numbering::Flow);
+    assert_eq!(
+      captions_of(&ast),
+      vec![
+        "OrderProduct->Order=1",
+        "Order->ProductOrdered=2",
+        "ValidateOrder->Order=5",
+        "Order->OrderValidated=6",
+        "ProductOrdered->ValidateOrderPolicy=3: when ordered",
+        "ValidateOrderPolicy->ValidateOrder=4",
+      ]
+    );
+  }
+
+  #[test]
+  fn test_number_edges_in_source_order() {
+    let ast = parse(b"c:A\ne:B\nA->B\nB->C\n").unwrap();
+    let ast = number_edges(&ast, &Numbering::Source);
+    assert_eq!(captions_of(&ast), vec!["A->B=1", "B->C=2"]);
+  }
+}
@@ -1,4 +1,4 @@
-use crate::ast::{Arrow, Ast, Line, Name};
+use crate::ast::{Arrow, Ast, Attribute, Caption, Field, FieldType, Line, Name, Setting};
 use oni_comb_parser_rs::prelude::*;
 use std::char::{decode_utf16, REPLACEMENT_CHARACTER};
 
@@ -10,7 +10,7 @@ fn space<'a>() -> Parser<'a, u8, ()> {
   elm_of(b" \t").of_many0().discard()
 }
 
-fn chars<'a>() -> Parser<'a, u8, String> {
+fn escape_sequence<'a>() -> Parser<'a, u8, &'a u8> {
   let special_char = elm_ref(b'\\')
     | elm_ref(b'/')
     | elm_ref(b'"')
@@ -19,8 +19,31 @@ fn chars<'a>() -> Parser<'a, u8, String> {
     | elm_ref(b'n').map(|_| &b'\n')
     | elm_ref(b'r').map(|_| &b'\r')
     | elm_ref(b't').map(|_| &b'\t');
-  let escape_sequence = elm_ref(b'\\') * special_char;
-  (none_ref_of(b"\\\":-\n") | escape_sequence)
+  elm_ref(b'\\') * special_char
+}
+
+// Characters that end a name, so that `:`, `->`, `--`, `{` and `@` can follow it.
+const NAME_DELIMITERS: &[u8] = b"\\\":-{}@\n";
+
+// Whether the formatter can write `s` as a name that parses back unchanged.
+pub(crate) fn is_name(s: &str) -> bool {
+  !s.is_empty() && s.trim() == s && !s.bytes().any(|b| NAME_DELIMITERS.contains(&b))
+}
+
+// Tags, attribute keys and field names.
+pub(crate) fn is_identifier(s: &str) -> bool {
+  !s.is_empty() && s.bytes().all(|b| b.is_ascii_alphanumeric() || b"_-.".contains(&b))
+}
+
+fn chars<'a>() -> Parser<'a, u8, String> {
+  (none_ref_of(NAME_DELIMITERS) | escape_sequence())
+    .map(Clone::clone)
+    .of_many1()
+    .map_res(String::from_utf8)
+}
+
+fn quoted_chars<'a>() -> Parser<'a, u8, String> {
+  (none_ref_of(b"\\\"\n") | escape_sequence())
     .map(Clone::clone)
     .of_many1()
     .map_res(String::from_utf8)
@@ -47,75 +70,192 @@ fn string<'a>() -> Parser<'a, u8, String> {
 fn utf16_string<'a>() -> Parser<'a, u8, String> {
   let str = surround(
     elm_ref(b'"'),
-    (chars().attempt() | utf16_chars()).of_many0(),
+    (quoted_chars().attempt() | utf16_chars()).of_many0(),
     elm_ref(b'"'),
   );
   str.map(|strings| strings.concat())
 }
 
+fn dedent(s: &str) -> String {
+  let mut lines = s.lines().collect::<Vec<_>>();
+  if lines.first().is_some_and(|line| line.trim().is_empty()) {
+    lines.remove(0);
+  }
+  if lines.last().is_some_and(|line| line.trim().is_empty()) {
+    lines.pop();
+  }
+  let indent_of = |line: &str| line.len() - line.trim_start_matches([' ', '\t']).len();
+  let indent = lines
+    .iter()
+    .filter(|line| !line.trim().is_empty())
+    .map(|line| indent_of(line))
+    .min()
+    .unwrap_or(0);
+  lines
+    .iter()
+    .map(|line| &line[indent.min(indent_of(line))..])
+    .collect::<Vec<_>>()
+    .join("\n")
+}
+
+fn multi_line_string<'a>() -> Parser<'a, u8, String> {
+  let body = (seq(b"\"\"\"").not() * elm_any_ref())
+    .map(Clone::clone)
+    .of_many0()
+    .map_res(String::from_utf8);
+  surround(seq(b"\"\"\""), body, seq(b"\"\"\"")).map(|s| dedent(&s))
+}
+
 fn name<'a>() -> Parser<'a, u8, String> {
-  space() * string() - space()
+  (space() * string() - space()).map(|s| s.trim_end().to_string())
 }
 
 fn caption_string<'a>() -> Parser<'a, u8, String> {
-  space() * utf16_string() - space()
+  space() * (multi_line_string().attempt() | utf16_string()) - space()
+}
+
+fn locale<'a>() -> Parser<'a, u8, String> {
+  (elm_alpha_ref() | elm_ref_of(b"_-"))
+    .map(Clone::clone)
+    .of_many1()
+    .map_res(String::from_utf8)
+}
+
+fn caption<'a>() -> Parser<'a, u8, Caption> {
+  let localized = space() * elm_ref(b':') * space() * locale().opt() + caption_string();
+  localized.attempt().of_many1().map(|captions| {
+    captions
+      .into_iter()
+      .fold(Caption::default(), |caption, (locale, text)| {
+        caption.with_locale(locale.unwrap_or_default(), text)
+      })
+  })
+}
+
+fn identifier<'a>() -> Parser<'a, u8, String> {
+  (elm_alpha_digit_ref() | elm_ref_of(b"_-."))
+    .map(Clone::clone)
+    .of_many1()
+    .map_res(String::from_utf8)
+}
+
+fn attribute_value<'a>() -> Parser<'a, u8, String> {
+  let bare_value = none_ref_of(b" \t\r\n,{}\"")
+    .map(Clone::clone)
+    .of_many1()
+    .map_res(String::from_utf8);
+  utf16_string() | bare_value
+}
+
+fn attribute<'a>() -> Parser<'a, u8, Attribute> {
+  let p = space_with_crlf() * identifier() - space() - elm_ref(b'=') - space() + attribute_value() - space_with_crlf();
+  p.map(|(key, value)| Attribute::new(key, value))
+}
+
+fn field_type<'a>() -> Parser<'a, u8, FieldType> {
+  let list_type = surround(elm_ref(b'[') - space(), lazy(field_type), space() * elm_ref(b']'));
+  list_type.map(|element_type| FieldType::List(Box::new(element_type))) | identifier().map(FieldType::Simple)
+}
+
+fn field<'a>() -> Parser<'a, u8, Field> {
+  let p = space_with_crlf() * identifier() - space() - elm_ref(b':') - space() + field_type() - space_with_crlf();
+  p.map(|(name, field_type)| Field::new(name, field_type))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Property {
+  Attribute(Attribute),
+  Field(Field),
+}
+
+fn property<'a>() -> Parser<'a, u8, Property> {
+  attribute().map(Property::Attribute).attempt() | field().map(Property::Field)
 }
 
-fn caption<'a>() -> Parser<'a, u8, String> {
-  space() * elm_ref(b':') * caption_string()
+fn properties<'a>() -> Parser<'a, u8, Vec<Property>> {
+  space()
+    * surround(
+      elm_ref(b'{'),
+      property().of_many0_sep(elm_ref(b',')),
+      space_with_crlf() * elm_ref(b'}'),
+    )
 }
 
-fn element_parser<'a, F, A>(l: u8, f: F) -> Parser<'a, u8, A>
+fn tags<'a>() -> Parser<'a, u8, Vec<String>> {
+  (space() * elm_ref(b'@') * identifier()).attempt().of_many0()
+}
+
+fn element_parser<'a, F>(l: u8, f: F) -> Parser<'a, u8, Name>
 where
-  F: Fn(String, Option<String>) -> A + 'a,
-  A: Clone + 'a,
+  F: Fn(String, Caption) -> Name + 'a,
 {
   let lp = elm_ref(l) + elm_ref(b':');
-  let p = space_with_crlf() * lp * name().debug("name") + caption().debug("caption").opt() - space_with_crlf();
-  p.map(move |(n, c)| f(n, c))
+  let p = space_with_crlf() * lp * name().debug("name")
+    + caption().debug("caption").opt()
+    + tags().debug("tags")
+    + properties().debug("properties").opt()
+    - space_with_crlf();
+  p.map(move |(((n, c), tags), properties)| {
+    let mut attributes = Vec::new();
+    let mut fields = Vec::new();
+    for property in properties.unwrap_or_default() {
+      match property {
+        Property::Attribute(attribute) => attributes.push(attribute),
+        Property::Field(field) => fields.push(field),
+      }
+    }
+    f(n, c.unwrap_or_default())
+      .with_attributes(attributes)
+      .with_fields(fields)
+      .with_tags(tags)
+  })
 }
 
 fn title<'a>() -> Parser<'a, u8, Ast> {
-  element_parser(b't', |n, c| Ast::TitleDef(Name::of_title(n, c)))
+  element_parser(b't', Name::of_title).map(Ast::TitleDef)
 }
 
 fn user<'a>() -> Parser<'a, u8, Ast> {
-  element_parser(b'u', |n, c| Ast::NameDef(Name::of_user(n, c)))
+  element_parser(b'u', Name::of_user).map(Ast::NameDef)
 }
 
 fn command<'a>() -> Parser<'a, u8, Ast> {
-  element_parser(b'c', |n, c| Ast::NameDef(Name::of_command(n, c)))
+  element_parser(b'c', Name::of_command).map(Ast::NameDef)
 }
 
 fn event<'a>() -> Parser<'a, u8, Ast> {
-  element_parser(b'e', |n, c| Ast::NameDef(Name::of_event(n, c)))
+  element_parser(b'e', Name::of_event).map(Ast::NameDef)
 }
 
 fn aggregate<'a>() -> Parser<'a, u8, Ast> {
-  element_parser(b'a', |n, c| Ast::NameDef(Name::of_aggregate(n, c)))
+  element_parser(b'a', Name::of_aggregate).map(Ast::NameDef)
 }
 
 fn policy<'a>() -> Parser<'a, u8, Ast> {
-  element_parser(b'p', |n, c| Ast::NameDef(Name::of_policy(n, c)))
+  element_parser(b'p', Name::of_policy).map(Ast::NameDef)
 }
 
 fn read_model<'a>() -> Parser<'a, u8, Ast> {
-  element_parser(b'r', |n, c| Ast::NameDef(Name::of_read_model(n, c)))
+  element_parser(b'r', Name::of_read_model).map(Ast::NameDef)
+}
+
+fn hot_spot<'a>() -> Parser<'a, u8, Ast> {
+  element_parser(b'h', Name::of_hot_spot).map(Ast::NameDef)
 }
 
 fn element<'a>() -> Parser<'a, u8, Ast> {
-  space() * (title() | user() | command() | event() | aggregate() | policy() | read_model()) - space()
+  space() * (title() | user() | command() | event() | aggregate() | policy() | read_model() | hot_spot()) - space()
 }
 
 fn relation_ship_parser<'a, F, A>(b: u8, f: F) -> Parser<'a, u8, A>
 where
-  F: Fn(String, String, Option<String>) -> A + 'a,
+  F: Fn(String, String, Caption) -> A + 'a,
   A: Clone + 'a,
 {
   let p =
     space_with_crlf() * name().debug("from") + (elm_ref(b'-') + elm_ref(b)) * name().debug("to") + caption().opt()
       - space_with_crlf();
-  p.map(move |((from, to), c)| f(from, to, c))
+  p.map(move |((from, to), c)| f(from, to, c.unwrap_or_default()))
 }
 
 fn arrow<'a>() -> Parser<'a, u8, Ast> {
@@ -130,22 +270,51 @@ fn relation_ship<'a>() -> Parser<'a, u8, Ast> {
   space() * (line().attempt() | arrow()) - space()
 }
 
+fn include<'a>() -> Parser<'a, u8, Ast> {
+  let p = space_with_crlf() * seq(b"include") * space() * utf16_string() - space_with_crlf();
+  p.map(Ast::Include)
+}
+
+fn setting_value<'a>() -> Parser<'a, u8, String> {
+  let line_value = none_ref_of(b"\r\n")
+    .map(Clone::clone)
+    .of_many1()
+    .map_res(String::from_utf8)
+    .map(|s| s.trim_end().to_string());
+  (utf16_string() - space()) | line_value
+}
+
+fn setting<'a>() -> Parser<'a, u8, Ast> {
+  let p = space_with_crlf() * elm_ref(b'!') * identifier() - space() + setting_value() - space_with_crlf();
+  p.map(|(key, value)| Ast::SettingDef(Setting::new(key, value)))
+}
+
+fn comment<'a>() -> Parser<'a, u8, Ast> {
+  let text = none_ref_of(b"\r\n")
+    .map(Clone::clone)
+    .of_many0()
+    .map_res(String::from_utf8);
+  let p = space_with_crlf() * elm_ref(b'#') * text - space_with_crlf();
+  p.map(|text| Ast::Comment(text.trim().to_string()))
+}
+
 fn document<'a>() -> Parser<'a, u8, Ast> {
-  space_with_crlf() * (element().attempt() | relation_ship()) - space_with_crlf()
+  space_with_crlf()
+    * (comment().attempt() | include().attempt() | setting().attempt() | element().attempt() | relation_ship())
+    - space_with_crlf()
 }
 
 pub fn documents<'a>() -> Parser<'a, u8, Ast> {
-  document().of_many0().map(|elements| Ast::Documents(elements))
+  document().of_many0().map(Ast::Documents)
 }
 
 pub fn parse<'a>(input: &'a [u8]) -> Result<Ast, ParseError<'a, u8>> {
-  documents().parse(input).to_result()
+  (documents() - end()).parse(input).to_result()
 }
 
 #[cfg(test)]
 pub mod tests {
   use super::*;
-  use crate::dot_writer::Title;
   use std::env;
 
   pub fn test_parser<'a, A>(parser: Parser<'a, u8, A>, input: &'a [u8], expected: A)
@@ -186,9 +355,64 @@ pub mod tests {
     test_parser(caption_string(), "\"ユーザ\"".as_bytes(), "ユーザ".to_string());
   }
 
+  #[test]
+  pub fn test_multi_line_caption_string() {
+    test_parser(
+      caption_string(),
+      "\"\"\"\n    **注文**が\n    \"確定\"された\n    \"\"\"".as_bytes(),
+      "**注文**が\n\"確定\"された".to_string(),
+    );
+  }
+
+  #[test]
+  pub fn test_escaped_line_break_in_caption_string() {
+    test_parser(caption_string(), br#""a\nb""#, "a\nb".to_string());
+  }
+
   #[test]
   pub fn test_caption() {
-    test_parser(caption(), ":\"ユーザ\"".as_bytes(), "ユーザ".to_string());
+    test_parser(caption(), ":\"ユーザ\"".as_bytes(), Caption::new("ユーザ".to_string()));
+  }
+
+  #[test]
+  pub fn test_localized_captions() {
+    test_parser(
+      caption(),
+      r#":ja"注文された":en"Order placed""#.as_bytes(),
+      Caption::default()
+        .with_locale("ja".to_string(), "注文された".to_string())
+        .with_locale("en".to_string(), "Order placed".to_string()),
+    );
+  }
+
+  #[test]
+  pub fn test_event_with_localized_captions() {
+    test_parser(
+      event(),
+      r#"e:OrderPlaced:ja"注文された":en"Order placed" @mvp"#.as_bytes(),
+      Ast::NameDef(
+        Name::of_event(
+          "OrderPlaced".to_string(),
+          Caption::default()
+            .with_locale("ja".to_string(), "注文された".to_string())
+            .with_locale("en".to_string(), "Order placed".to_string()),
+        )
+        .with_tags(vec!["mvp".to_string()]),
+      ),
+    );
+  }
+
+  #[test]
+  pub fn test_arrow_with_localized_captions() {
+    test_parser(
+      arrow(),
+      r#"abc->def:"既定":en"default""#.as_bytes(),
+      Ast::Arrow(Arrow::new(
+        "abc".to_string(),
+        "def".to_string(),
+        Caption::new("既定".to_string()).with_locale("en".to_string(), "default".to_string()),
+      )),
+    );
   }
 
   #[test]
@@ -196,7 +420,7 @@ pub mod tests {
     test_parser(
       user(),
       r#"u:abc"#.as_bytes(),
-      Ast::NameDef(Name::of_user("abc".to_string(), None)),
+      Ast::NameDef(Name::of_user("abc".to_string(), Caption::default())),
     );
   }
 
@@ -205,7 +429,7 @@ pub mod tests {
     test_parser(
       user(),
       r#"u:abc"#.as_bytes(),
-      Ast::NameDef(Name::of_user("abc".to_string(), None)),
+      Ast::NameDef(Name::of_user("abc".to_string(), Caption::default())),
     );
   }
 
@@ -214,7 +438,7 @@ pub mod tests {
     test_parser(
       user(),
       r#"u:abc:"ユーザ""#.as_bytes(),
-      Ast::NameDef(Name::of_user("abc".to_string(), Some("ユーザ".to_string()))),
+      Ast::NameDef(Name::of_user("abc".to_string(), Caption::new("ユーザ".to_string()))),
     );
   }
 
@@ -223,7 +447,7 @@ pub mod tests {
     test_parser(
       user(),
       r#"u:abc:"ユーザ""#.as_bytes(),
-      Ast::NameDef(Name::of_user("abc".to_string(), Some("ユーザ".to_string()))),
+      Ast::NameDef(Name::of_user("abc".to_string(), Caption::new("ユーザ".to_string()))),
     );
   }
 
@@ -232,7 +456,7 @@ pub mod tests {
     test_parser(
       command(),
       "c:abc".as_bytes(),
-      Ast::NameDef(Name::of_command("abc".to_string(), None)),
+      Ast::NameDef(Name::of_command("abc".to_string(), Caption::default())),
     );
   }
 
@@ -241,7 +465,7 @@ pub mod tests {
     test_parser(
       command(),
       r#"c:abc:"ユーザ""#.as_bytes(),
-      Ast::NameDef(Name::of_command("abc".to_string(), Some("ユーザ".to_string()))),
+      Ast::NameDef(Name::of_command("abc".to_string(), Caption::new("ユーザ".to_string()))),
     );
   }
 
@@ -250,7 +474,7 @@ pub mod tests {
     test_parser(
       event(),
       "e:abc".as_bytes(),
-      Ast::NameDef(Name::of_event("abc".to_string(), None)),
+      Ast::NameDef(Name::of_event("abc".to_string(), Caption::default())),
     );
   }
 
@@ -259,7 +483,7 @@ pub mod tests {
     test_parser(
       event(),
       r#"e:abc:"ユーザ""#.as_bytes(),
-      Ast::NameDef(Name::of_event("abc".to_string(), Some("ユーザ".to_string()))),
+      Ast::NameDef(Name::of_event("abc".to_string(), Caption::new("ユーザ".to_string()))),
     );
   }
 
@@ -268,7 +492,7 @@ pub mod tests {
     test_parser(
       aggregate(),
       "a:abc".as_bytes(),
-      Ast::NameDef(Name::of_aggregate("abc".to_string(), None)),
+      Ast::NameDef(Name::of_aggregate("abc".to_string(), Caption::default())),
     );
   }
 
@@ -277,7 +501,10 @@ pub mod tests {
     test_parser(
       aggregate(),
       r#"a:abc:"ユーザ""#.as_bytes(),
-      Ast::NameDef(Name::of_aggregate("abc".to_string(), Some("ユーザ".to_string()))),
+      Ast::NameDef(Name::of_aggregate(
+        "abc".to_string(),
+        Caption::new("ユーザ".to_string()),
+      )),
     );
   }
 
@@ -286,40 +513,159 @@ pub mod tests {
     test_parser(
       policy(),
       r#"p:abc"#.as_bytes(),
-      Ast::NameDef(Name::of_policy("abc".to_string(), None)),
+      Ast::NameDef(Name::of_policy("abc".to_string(), Caption::default())),
     );
   }
 
   #[test]
   pub fn test_policy_with_caption() {
     env::set_var("RUST_LOG", "debug");
-    env_logger::init();
+    let _ = env_logger::try_init();
     test_parser(
       policy(),
       r#"p:abc:"ユーザ""#.as_bytes(),
-      Ast::NameDef(Name::of_policy("abc".to_string(), Some("ユーザ".to_string()))),
+      Ast::NameDef(Name::of_policy("abc".to_string(), Caption::new("ユーザ".to_string()))),
     );
   }
 
   #[test]
   pub fn test_read_model() {
     env::set_var("RUST_LOG", "debug");
-    env_logger::init();
+    let _ = env_logger::try_init();
     test_parser(
       read_model(),
       "r:abc".as_bytes(),
-      Ast::NameDef(Name::of_read_model("abc".to_string(), None)),
+      Ast::NameDef(Name::of_read_model("abc".to_string(), Caption::default())),
     );
   }
 
   #[test]
   pub fn test_read_model_with_caption() {
     env::set_var("RUST_LOG", "debug");
-    env_logger::init();
+    let _ = env_logger::try_init();
     test_parser(
       read_model(),
       r#"r:abc:"ユーザ""#.as_bytes(),
-      Ast::NameDef(Name::of_read_model("abc".to_string(), Some("ユーザ".to_string()))),
+      Ast::NameDef(Name::of_read_model(
+        "abc".to_string(),
+        Caption::new("ユーザ".to_string()),
+      )),
+    );
+  }
+
+  #[test]
+  pub fn test_hot_spot_with_caption() {
+    env::set_var("RUST_LOG", "debug");
+    let _ = env_logger::try_init();
+    test_parser(
+      hot_spot(),
+      r#"h:abc:"在庫切れ?""#.as_bytes(),
+      Ast::NameDef(Name::of_hot_spot(
+        "abc".to_string(),
+        Caption::new("在庫切れ?".to_string()),
+      )),
+    );
+  }
+
+  #[test]
+  pub fn test_properties() {
+    test_parser(
+      properties(),
+      r#"{ owner = "team-a", sla = "5s", status = draft }"#.as_bytes(),
+      vec![
+        Property::Attribute(Attribute::new("owner".to_string(), "team-a".to_string())),
+        Property::Attribute(Attribute::new("sla".to_string(), "5s".to_string())),
+        Property::Attribute(Attribute::new("status".to_string(), "draft".to_string())),
+      ],
+    );
+  }
+
+  #[test]
+  pub fn test_field_type() {
+    test_parser(
+      field_type(),
+      b"[[LineItem]]",
+      FieldType::List(Box::new(FieldType::List(Box::new(FieldType::Simple(
+        "LineItem".to_string(),
+      ))))),
+    );
+  }
+
+  #[test]
+  pub fn test_event_with_tags() {
+    test_parser(
+      event(),
+      r#"e:OrderPlaced:"注文された" @mvp @team-payments { owner = "team-a" }"#.as_bytes(),
+      Ast::NameDef(
+        Name::of_event("OrderPlaced".to_string(), Caption::new("注文された".to_string()))
+          .with_attributes(vec![Attribute::new("owner".to_string(), "team-a".to_string())])
+          .with_tags(vec!["mvp".to_string(), "team-payments".to_string()]),
+      ),
+    );
+    test_parser(
+      event(),
+      b"e:OrderPlaced @phase2",
+      Ast::NameDef(Name::of_event("OrderPlaced".to_string(), Caption::default()).with_tags(vec!["phase2".to_string()])),
+    );
+  }
+
+  #[test]
+  pub fn test_event_with_fields() {
+    test_parser(
+      event(),
+      r#"e:OrderPlaced { orderId: OrderId, items: [LineItem], total: Money }"#.as_bytes(),
+      Ast::NameDef(
+        Name::of_event("OrderPlaced".to_string(), Caption::default()).with_fields(vec![
+          Field::new("orderId".to_string(), FieldType::Simple("OrderId".to_string())),
+          Field::new(
+            "items".to_string(),
+            FieldType::List(Box::new(FieldType::Simple("LineItem".to_string()))),
+          ),
+          Field::new("total".to_string(), FieldType::Simple("Money".to_string())),
+        ]),
+      ),
+    );
+  }
+
+  #[test]
+  pub fn test_command_with_caption_fields_and_attributes() {
+    test_parser(
+      command(),
+      r#"c:PlaceOrder:"注文する" { owner = "team-a", orderId: OrderId }"#.as_bytes(),
+      Ast::NameDef(
+        Name::of_command("PlaceOrder".to_string(), Caption::new("注文する".to_string()))
+          .with_attributes(vec![Attribute::new("owner".to_string(), "team-a".to_string())])
+          .with_fields(vec![Field::new(
+            "orderId".to_string(),
+            FieldType::Simple("OrderId".to_string()),
+          )]),
+      ),
+    );
+  }
+
+  #[test]
+  pub fn test_event_with_caption_and_attributes() {
+    test_parser(
+      event(),
+      r#"e:OrderPlaced:"注文された" { owner = "team-a", status = draft }"#.as_bytes(),
+      Ast::NameDef(
+        Name::of_event("OrderPlaced".to_string(), Caption::new("注文された".to_string())).with_attributes(vec![
+          Attribute::new("owner".to_string(), "team-a".to_string()),
+          Attribute::new("status".to_string(), "draft".to_string()),
+        ]),
+      ),
+    );
+  }
+
+  #[test]
+  pub fn test_event_with_attributes() {
+    test_parser(
+      event(),
+      r#"e:OrderPlaced { owner = "team-a" }"#.as_bytes(),
+      Ast::NameDef(
+        Name::of_event("OrderPlaced".to_string(), Caption::default())
+          .with_attributes(vec![Attribute::new("owner".to_string(), "team-a".to_string())]),
+      ),
     );
   }
 
@@ -328,7 +674,7 @@ pub mod tests {
     test_parser(
       arrow(),
       r#"abc->def"#.as_bytes(),
-      Ast::Arrow(Arrow::new("abc".to_string(), "def".to_string(), None)),
+      Ast::Arrow(Arrow::new("abc".to_string(), "def".to_string(), Caption::default())),
     );
   }
 
@@ -340,7 +686,7 @@ pub mod tests {
       Ast::Arrow(Arrow::new(
         "abc".to_string(),
         "def".to_string(),
-        Some("ユーザ".to_string()),
+        Caption::new("ユーザ".to_string()),
       )),
     );
   }
@@ -350,7 +696,7 @@ pub mod tests {
     test_parser(
       line(),
       r#"abc--def"#.as_bytes(),
-      Ast::Line(Line::new("abc".to_string(), "def".to_string(), None)),
+      Ast::Line(Line::new("abc".to_string(), "def".to_string(), Caption::default())),
     );
   }
 
@@ -362,17 +708,65 @@ pub mod tests {
       Ast::Line(Line::new(
         "abc".to_string(),
         "def".to_string(),
-        Some("ユーザ".to_string()),
+        Caption::new("ユーザ".to_string()),
       )),
     );
   }
 
+  #[test]
+  pub fn test_comment() {
+    test_parser(
+      documents(),
+      b"# ordering\ne:OrderPlaced\n  #  end  \n",
+      Ast::Documents(vec![
+        Ast::Comment("ordering".to_string()),
+        Ast::NameDef(Name::of_event("OrderPlaced".to_string(), Caption::default())),
+        Ast::Comment("end".to_string()),
+      ]),
+    );
+  }
+
+  #[test]
+  pub fn test_include() {
+    test_parser(
+      include(),
+      r#"include "payments.eg""#.as_bytes(),
+      Ast::Include("payments.eg".to_string()),
+    );
+  }
+
+  #[test]
+  pub fn test_setting() {
+    test_parser(
+      setting(),
+      b"!direction TB",
+      Ast::SettingDef(Setting::new("direction".to_string(), "TB".to_string())),
+    );
+    test_parser(
+      setting(),
+      b"!show captions \n",
+      Ast::SettingDef(Setting::new("show".to_string(), "captions".to_string())),
+    );
+    test_parser(
+      setting(),
+      br#"!theme "dark""#,
+      Ast::SettingDef(Setting::new("theme".to_string(), "dark".to_string())),
+    );
+  }
+
+  #[test]
+  pub fn test_parse_rejects_trailing_garbage() {
+    assert!(parse(r#"e:abc:"ユーザ" ""#.as_bytes()).is_err());
+  }
+
   #[test]
   pub fn test_documents() {
     test_parser(
       documents(),
       r#"
         t:G:"title"
+        !direction TB
+        include "payments.eg"
         u:abc:"ユーザ"
         c:abc:"ユーザ"
         e:abc:"ユーザ"
@@ -384,22 +778,30 @@ pub mod tests {
         "#
       .as_bytes(),
       Ast::Documents(vec![
-        Ast::TitleDef(Name::of_title("G".to_string(), Some("title".to_string()))),
-        Ast::NameDef(Name::of_user("abc".to_string(), Some("ユーザ".to_string()))),
-        Ast::NameDef(Name::of_command("abc".to_string(), Some("ユーザ".to_string()))),
-        Ast::NameDef(Name::of_event("abc".to_string(), Some("ユーザ".to_string()))),
-        Ast::NameDef(Name::of_aggregate("abc".to_string(), Some("ユーザ".to_string()))),
-        Ast::NameDef(Name::of_policy("abc".to_string(), Some("ユーザ".to_string()))),
-        Ast::NameDef(Name::of_read_model("abc".to_string(), Some("ユーザ".to_string()))),
+        Ast::TitleDef(Name::of_title("G".to_string(), Caption::new("title".to_string()))),
+        Ast::SettingDef(Setting::new("direction".to_string(), "TB".to_string())),
+        Ast::Include("payments.eg".to_string()),
+        Ast::NameDef(Name::of_user("abc".to_string(), Caption::new("ユーザ".to_string()))),
+        Ast::NameDef(Name::of_command("abc".to_string(), Caption::new("ユーザ".to_string()))),
+        Ast::NameDef(Name::of_event("abc".to_string(), Caption::new("ユーザ".to_string()))),
+        Ast::NameDef(Name::of_aggregate(
+          "abc".to_string(),
+          Caption::new("ユーザ".to_string()),
+        )),
+        Ast::NameDef(Name::of_policy("abc".to_string(), Caption::new("ユーザ".to_string()))),
+        Ast::NameDef(Name::of_read_model(
+          "abc".to_string(),
+          Caption::new("ユーザ".to_string()),
+        )),
         Ast::Arrow(Arrow::new(
           "abc".to_string(),
           "def".to_string(),
-          Some("ユーザ".to_string()),
+          Caption::new("ユーザ".to_string()),
         )),
         Ast::Arrow(Arrow::new(
           "abc".to_string(),
           "def".to_string(),
-          Some("ユーザ".to_string()),
+          Caption::new("ユーザ".to_string()),
         )),
       ]),
     );
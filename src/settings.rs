@@ -0,0 +1,550 @@
+use std::fmt;
+use std::fmt::Formatter;
+use std::str::FromStr;
+
+use crate::ast::name_type::NameType;
+use crate::ast::Setting;
+use crate::tag_expr::TagExpr;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SettingsError {
+  UnknownKey(String),
+  InvalidValue { key: String, value: String },
+}
+
+impl fmt::Display for SettingsError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match self {
+      SettingsError::UnknownKey(key) => write!(f, "unknown setting: {}", key),
+      SettingsError::InvalidValue { key, value } => write!(f, "invalid value for {}: {}", key, value),
+    }
+  }
+}
+
+impl std::error::Error for SettingsError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Direction {
+  LeftToRight,
+  RightToLeft,
+  TopToBottom,
+  BottomToTop,
+}
+
+impl FromStr for Direction {
+  type Err = ();
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_uppercase().as_str() {
+      "LR" => Ok(Direction::LeftToRight),
+      "RL" => Ok(Direction::RightToLeft),
+      "TB" => Ok(Direction::TopToBottom),
+      "BT" => Ok(Direction::BottomToTop),
+      _ => Err(()),
+    }
+  }
+}
+
+impl fmt::Display for Direction {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match self {
+      Direction::LeftToRight => write!(f, "LR"),
+      Direction::RightToLeft => write!(f, "RL"),
+      Direction::TopToBottom => write!(f, "TB"),
+      Direction::BottomToTop => write!(f, "BT"),
+    }
+  }
+}
+
+impl Direction {
+  pub fn across(&self) -> Direction {
+    match self {
+      Direction::LeftToRight | Direction::RightToLeft => Direction::TopToBottom,
+      Direction::TopToBottom | Direction::BottomToTop => Direction::LeftToRight,
+    }
+  }
+
+  pub fn is_reversed(&self) -> bool {
+    matches!(self, Direction::RightToLeft | Direction::BottomToTop)
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Splines {
+  None,
+  Line,
+  Polyline,
+  Curved,
+  Ortho,
+  Spline,
+}
+
+impl FromStr for Splines {
+  type Err = ();
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "none" => Ok(Splines::None),
+      "line" => Ok(Splines::Line),
+      "polyline" => Ok(Splines::Polyline),
+      "curved" => Ok(Splines::Curved),
+      "ortho" => Ok(Splines::Ortho),
+      "spline" => Ok(Splines::Spline),
+      _ => Err(()),
+    }
+  }
+}
+
+impl fmt::Display for Splines {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match self {
+      Splines::None => write!(f, "none"),
+      Splines::Line => write!(f, "line"),
+      Splines::Polyline => write!(f, "polyline"),
+      Splines::Curved => write!(f, "curved"),
+      Splines::Ortho => write!(f, "ortho"),
+      Splines::Spline => write!(f, "spline"),
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Theme {
+  Light,
+  Dark,
+}
+
+impl FromStr for Theme {
+  type Err = ();
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "light" => Ok(Theme::Light),
+      "dark" => Ok(Theme::Dark),
+      _ => Err(()),
+    }
+  }
+}
+
+impl Theme {
+  pub fn fill_color(&self, name_type: &NameType) -> &'static str {
+    match self {
+      Theme::Light => match name_type {
+        NameType::User => "lightyellow",
+        NameType::Command => "lightblue",
+        NameType::Event => "orange",
+        NameType::Aggregate => "lightyellow",
+        NameType::Policy => "plum",
+        NameType::ReadModel => "lightgreen",
+        NameType::HotSpot => "lightpink",
+        NameType::Title => "white",
+      },
+      Theme::Dark => match name_type {
+        NameType::User => "khaki4",
+        NameType::Command => "steelblue4",
+        NameType::Event => "darkorange3",
+        NameType::Aggregate => "goldenrod4",
+        NameType::Policy => "mediumorchid4",
+        NameType::ReadModel => "seagreen4",
+        NameType::HotSpot => "deeppink4",
+        NameType::Title => "gray15",
+      },
+    }
+  }
+
+  pub fn background_color(&self) -> &'static str {
+    match self {
+      Theme::Light => "white",
+      Theme::Dark => "gray15",
+    }
+  }
+
+  pub fn font_color(&self) -> &'static str {
+    match self {
+      Theme::Light => "black",
+      Theme::Dark => "white",
+    }
+  }
+
+  pub fn line_color(&self) -> &'static str {
+    match self {
+      Theme::Light => "black",
+      Theme::Dark => "gray80",
+    }
+  }
+
+  pub fn dimmed_fill_color(&self) -> &'static str {
+    match self {
+      Theme::Light => "gray92",
+      Theme::Dark => "gray25",
+    }
+  }
+
+  pub fn dimmed_color(&self) -> &'static str {
+    match self {
+      Theme::Light => "gray70",
+      Theme::Dark => "gray45",
+    }
+  }
+}
+
+// Hex values of the Graphviz colour names used by the themes and diff styles, for writers that are
+// not rendered by Graphviz. Other colours are returned unchanged.
+pub fn hex_color(color: &str) -> String {
+  match color {
+    "white" => "#FFFFFF",
+    "black" => "#000000",
+    "lightyellow" => "#FFFFE0",
+    "lightblue" => "#ADD8E6",
+    "orange" => "#FFA500",
+    "plum" => "#DDA0DD",
+    "lightgreen" => "#90EE90",
+    "lightpink" => "#FFB6C1",
+    "khaki4" => "#8B864E",
+    "steelblue4" => "#36648B",
+    "darkorange3" => "#CD6600",
+    "goldenrod4" => "#8B6914",
+    "mediumorchid4" => "#7A378B",
+    "seagreen4" => "#2E8B57",
+    "deeppink4" => "#8B0A50",
+    "gray15" => "#262626",
+    "gray25" => "#404040",
+    "gray45" => "#737373",
+    "gray70" => "#B3B3B3",
+    "gray80" => "#CCCCCC",
+    "gray92" => "#EBEBEB",
+    "green3" => "#00CD00",
+    "red" => "#FF0000",
+    "blue" => "#0000FF",
+    other => other,
+  }
+  .to_string()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LabelDisplay {
+  Name,
+  Caption,
+  Both,
+}
+
+impl FromStr for LabelDisplay {
+  type Err = ();
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "name" => Ok(LabelDisplay::Name),
+      "caption" => Ok(LabelDisplay::Caption),
+      "both" => Ok(LabelDisplay::Both),
+      _ => Err(()),
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TagFilterMode {
+  Hide,
+  Grey,
+}
+
+impl FromStr for TagFilterMode {
+  type Err = ();
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "hide" => Ok(TagFilterMode::Hide),
+      "grey" | "gray" => Ok(TagFilterMode::Grey),
+      _ => Err(()),
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Numbering {
+  Off,
+  Flow,
+  Source,
+}
+
+impl FromStr for Numbering {
+  type Err = ();
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "off" | "none" => Ok(Numbering::Off),
+      "flow" => Ok(Numbering::Flow),
+      "source" => Ok(Numbering::Source),
+      _ => Err(()),
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Layout {
+  Default,
+  Timeline,
+}
+
+impl FromStr for Layout {
+  type Err = ();
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "default" => Ok(Layout::Default),
+      "timeline" => Ok(Layout::Timeline),
+      _ => Err(()),
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TitlePosition {
+  Hidden,
+  Top,
+  Bottom,
+}
+
+impl FromStr for TitlePosition {
+  type Err = ();
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "none" => Ok(TitlePosition::Hidden),
+      "top" => Ok(TitlePosition::Top),
+      "bottom" => Ok(TitlePosition::Bottom),
+      _ => Err(()),
+    }
+  }
+}
+
+impl TitlePosition {
+  // Graphviz `labelloc` of the graph label.
+  pub fn labelloc(&self) -> Option<&'static str> {
+    match self {
+      TitlePosition::Hidden => None,
+      TitlePosition::Top => Some("t"),
+      TitlePosition::Bottom => Some("b"),
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NodeLabel {
+  Plain,
+  Table,
+}
+
+impl FromStr for NodeLabel {
+  type Err = ();
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "plain" => Ok(NodeLabel::Plain),
+      "table" => Ok(NodeLabel::Table),
+      _ => Err(()),
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+  pub direction: Direction,
+  pub theme: Theme,
+  pub splines: Option<Splines>,
+  pub label_display: LabelDisplay,
+  pub tag_filter: Option<TagExpr>,
+  pub tag_filter_mode: TagFilterMode,
+  pub numbering: Numbering,
+  pub layout: Layout,
+  pub node_label: NodeLabel,
+  pub locales: Vec<String>,
+  pub show_legend: bool,
+  pub show_attributes: bool,
+  pub url_pattern: Option<String>,
+  pub title_position: TitlePosition,
+}
+
+impl Default for Settings {
+  fn default() -> Self {
+    Self {
+      direction: Direction::LeftToRight,
+      theme: Theme::Light,
+      splines: None,
+      label_display: LabelDisplay::Caption,
+      tag_filter: None,
+      tag_filter_mode: TagFilterMode::Hide,
+      numbering: Numbering::Off,
+      layout: Layout::Default,
+      node_label: NodeLabel::Plain,
+      locales: Vec::new(),
+      show_legend: false,
+      show_attributes: true,
+      url_pattern: None,
+      title_position: TitlePosition::Hidden,
+    }
+  }
+}
+
+impl Settings {
+  pub fn apply(&mut self, setting: &Setting) -> Result<(), SettingsError> {
+    let invalid_value = || SettingsError::InvalidValue {
+      key: setting.key.clone(),
+      value: setting.value.clone(),
+    };
+    match setting.key.as_str() {
+      "direction" => self.direction = Direction::from_str(&setting.value).map_err(|_| invalid_value())?,
+      "theme" => self.theme = Theme::from_str(&setting.value).map_err(|_| invalid_value())?,
+      "splines" => self.splines = Some(Splines::from_str(&setting.value).map_err(|_| invalid_value())?),
+      "show" | "hide" => match setting.value.as_str() {
+        "captions" if setting.key == "show" => self.label_display = LabelDisplay::Caption,
+        "captions" => self.label_display = LabelDisplay::Name,
+        "legend" => self.show_legend = setting.key == "show",
+        "attributes" => self.show_attributes = setting.key == "show",
+        _ => return Err(invalid_value()),
+      },
+      "display" => self.label_display = LabelDisplay::from_str(&setting.value).map_err(|_| invalid_value())?,
+      "tags" => self.tag_filter = Some(TagExpr::from_str(&setting.value).map_err(|_| invalid_value())?),
+      "tag_mode" => self.tag_filter_mode = TagFilterMode::from_str(&setting.value).map_err(|_| invalid_value())?,
+      "numbering" => self.numbering = Numbering::from_str(&setting.value).map_err(|_| invalid_value())?,
+      "layout" => self.layout = Layout::from_str(&setting.value).map_err(|_| invalid_value())?,
+      "locale" => {
+        let locales = setting
+          .value
+          .split(',')
+          .map(|locale| locale.trim().to_string())
+          .collect::<Vec<_>>();
+        if locales.iter().any(String::is_empty) {
+          return Err(invalid_value());
+        }
+        self.locales = locales;
+      }
+      "url" => self.url_pattern = Some(setting.value.clone()),
+      "node_label" => self.node_label = NodeLabel::from_str(&setting.value).map_err(|_| invalid_value())?,
+      "title" => self.title_position = TitlePosition::from_str(&setting.value).map_err(|_| invalid_value())?,
+      _ => return Err(SettingsError::UnknownKey(setting.key.clone())),
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_apply() {
+    let mut settings = Settings::default();
+    settings
+      .apply(&Setting::new("direction".to_string(), "TB".to_string()))
+      .unwrap();
+    settings
+      .apply(&Setting::new("theme".to_string(), "dark".to_string()))
+      .unwrap();
+    settings
+      .apply(&Setting::new("splines".to_string(), "ortho".to_string()))
+      .unwrap();
+    settings
+      .apply(&Setting::new("hide".to_string(), "captions".to_string()))
+      .unwrap();
+    assert_eq!(settings.label_display, LabelDisplay::Name);
+    settings
+      .apply(&Setting::new("display".to_string(), "both".to_string()))
+      .unwrap();
+    settings
+      .apply(&Setting::new("tags".to_string(), "mvp".to_string()))
+      .unwrap();
+    settings
+      .apply(&Setting::new("tag_mode".to_string(), "grey".to_string()))
+      .unwrap();
+    settings
+      .apply(&Setting::new("numbering".to_string(), "flow".to_string()))
+      .unwrap();
+    settings
+      .apply(&Setting::new("layout".to_string(), "timeline".to_string()))
+      .unwrap();
+    settings
+      .apply(&Setting::new("node_label".to_string(), "table".to_string()))
+      .unwrap();
+    settings
+      .apply(&Setting::new("locale".to_string(), "en, ja".to_string()))
+      .unwrap();
+    settings
+      .apply(&Setting::new("show".to_string(), "legend".to_string()))
+      .unwrap();
+    settings
+      .apply(&Setting::new(
+        "url".to_string(),
+        "https://wiki/{type}/{name}".to_string(),
+      ))
+      .unwrap();
+    settings
+      .apply(&Setting::new("title".to_string(), "bottom".to_string()))
+      .unwrap();
+    settings
+      .apply(&Setting::new("hide".to_string(), "attributes".to_string()))
+      .unwrap();
+    assert_eq!(
+      settings,
+      Settings {
+        direction: Direction::TopToBottom,
+        theme: Theme::Dark,
+        splines: Some(Splines::Ortho),
+        label_display: LabelDisplay::Both,
+        tag_filter: Some(TagExpr::Tag("mvp".to_string())),
+        tag_filter_mode: TagFilterMode::Grey,
+        numbering: Numbering::Flow,
+        layout: Layout::Timeline,
+        node_label: NodeLabel::Table,
+        locales: vec!["en".to_string(), "ja".to_string()],
+        show_legend: true,
+        show_attributes: false,
+        url_pattern: Some("https://wiki/{type}/{name}".to_string()),
+        title_position: TitlePosition::Bottom,
+      }
+    );
+  }
+
+  #[test]
+  fn test_apply_rejects_invalid_settings() {
+    let mut settings = Settings::default();
+    assert_eq!(
+      settings.apply(&Setting::new("direction".to_string(), "XY".to_string())),
+      Err(SettingsError::InvalidValue {
+        key: "direction".to_string(),
+        value: "XY".to_string(),
+      })
+    );
+    assert_eq!(
+      settings.apply(&Setting::new("colour".to_string(), "red".to_string())),
+      Err(SettingsError::UnknownKey("colour".to_string()))
+    );
+  }
+
+  #[test]
+  fn test_theme_colors_have_hex_values() {
+    for theme in [Theme::Light, Theme::Dark] {
+      let mut colors = vec![
+        theme.background_color(),
+        theme.font_color(),
+        theme.line_color(),
+        theme.dimmed_fill_color(),
+        theme.dimmed_color(),
+      ];
+      for name_type in [
+        NameType::Title,
+        NameType::User,
+        NameType::Command,
+        NameType::Event,
+        NameType::Aggregate,
+        NameType::Policy,
+        NameType::ReadModel,
+        NameType::HotSpot,
+      ] {
+        colors.push(theme.fill_color(&name_type));
+      }
+      for color in colors {
+        assert!(hex_color(color).starts_with('#'), "{}", color);
+      }
+    }
+  }
+}
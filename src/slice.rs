@@ -0,0 +1,223 @@
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::fmt::Formatter;
+use std::str::FromStr;
+
+use crate::ast::Ast;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SliceError {
+  UnknownFocus(String),
+}
+
+impl fmt::Display for SliceError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match self {
+      SliceError::UnknownFocus(name) => write!(f, "unknown focus element: {}", name),
+    }
+  }
+}
+
+impl std::error::Error for SliceError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SliceDirection {
+  Upstream,
+  Downstream,
+  Both,
+}
+
+impl FromStr for SliceDirection {
+  type Err = ();
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "upstream" | "up" => Ok(SliceDirection::Upstream),
+      "downstream" | "down" => Ok(SliceDirection::Downstream),
+      "both" => Ok(SliceDirection::Both),
+      _ => Err(()),
+    }
+  }
+}
+
+struct Link {
+  from: String,
+  to: String,
+  directed: bool,
+}
+
+fn collect_links(ast: &Ast, links: &mut Vec<Link>, names: &mut HashSet<String>) {
+  match ast {
+    Ast::NameDef(name) => {
+      names.insert(name.name.clone());
+    }
+    Ast::Arrow(arrow) => links.push(Link {
+      from: arrow.from_ref.clone(),
+      to: arrow.to_ref.clone(),
+      directed: true,
+    }),
+    Ast::Line(line) => links.push(Link {
+      from: line.from_ref.clone(),
+      to: line.to_ref.clone(),
+      directed: false,
+    }),
+    Ast::Documents(documents) => {
+      for document in documents {
+        collect_links(document, links, names);
+      }
+    }
+    _ => {}
+  }
+}
+
+fn neighbours<'a>(links: &'a [Link], name: &'a str, direction: &SliceDirection) -> impl Iterator<Item = &'a str> {
+  let forward = *direction != SliceDirection::Upstream;
+  let backward = *direction != SliceDirection::Downstream;
+  links.iter().filter_map(move |link| {
+    if link.from == name && (forward || !link.directed) {
+      Some(link.to.as_str())
+    } else if link.to == name && (backward || !link.directed) {
+      Some(link.from.as_str())
+    } else {
+      None
+    }
+  })
+}
+
+// `Both` is the union of the upstream and downstream slices; following every edge both ways instead
+// would pull in whole branches that merely feed into a downstream element.
+fn reachable_names(links: &[Link], focus: &str, direction: &SliceDirection, depth: Option<usize>) -> HashSet<String> {
+  if *direction == SliceDirection::Both {
+    let mut reached = reachable_names(links, focus, &SliceDirection::Upstream, depth);
+    reached.extend(reachable_names(links, focus, &SliceDirection::Downstream, depth));
+    return reached;
+  }
+  let mut reached = HashSet::from([focus.to_string()]);
+  let mut queue = VecDeque::from([(focus.to_string(), 0)]);
+  while let Some((name, distance)) = queue.pop_front() {
+    if depth.is_some_and(|depth| distance >= depth) {
+      continue;
+    }
+    for next in neighbours(links, &name, direction) {
+      if reached.insert(next.to_string()) {
+        queue.push_back((next.to_string(), distance + 1));
+      }
+    }
+  }
+  reached
+}
+
+fn retain(ast: &Ast, names: &HashSet<String>) -> Option<Ast> {
+  match ast {
+    Ast::NameDef(name) if !names.contains(&name.name) => None,
+    Ast::Arrow(arrow) if !names.contains(&arrow.from_ref) || !names.contains(&arrow.to_ref) => None,
+    Ast::Line(line) if !names.contains(&line.from_ref) || !names.contains(&line.to_ref) => None,
+    Ast::Documents(documents) => Some(Ast::Documents(
+      documents
+        .iter()
+        .filter_map(|document| retain(document, names))
+        .collect(),
+    )),
+    other => Some(other.clone()),
+  }
+}
+
+pub fn slice(ast: &Ast, focus: &str, direction: SliceDirection, depth: Option<usize>) -> Result<Ast, SliceError> {
+  let mut links = Vec::new();
+  let mut names = HashSet::new();
+  collect_links(ast, &mut links, &mut names);
+  let referenced = links.iter().any(|link| link.from == focus || link.to == focus);
+  if !names.contains(focus) && !referenced {
+    return Err(SliceError::UnknownFocus(focus.to_string()));
+  }
+  let reached = reachable_names(&links, focus, &direction, depth);
+  Ok(retain(ast, &reached).unwrap_or(Ast::Empty))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parsers::parse;
+
+  fn names_of(ast: &Ast) -> Vec<String> {
+    match ast {
+      Ast::NameDef(name) => vec![name.name.clone()],
+      Ast::Documents(documents) => documents.iter().flat_map(names_of).collect(),
+      _ => vec![],
+    }
+  }
+
+  fn order_flow() -> Ast {
+    parse(
+      br#"
+        c:OrderProduct
+        a:Order
+        e:ProductOrdered
+        p:ReserveInventoryPolicy
+        c:ReserveInventory
+        a:Inventory
+        e:InventoryReserved
+        r:OrderHistory
+        OrderProduct->Order
+        Order->ProductOrdered
+        ProductOrdered->ReserveInventoryPolicy
+        ReserveInventoryPolicy->ReserveInventory
+        ReserveInventory->Inventory
+        Inventory->InventoryReserved
+        ProductOrdered--OrderHistory
+        "#,
+    )
+    .unwrap()
+  }
+
+  #[test]
+  fn test_slice_upstream() {
+    let ast = slice(&order_flow(), "ProductOrdered", SliceDirection::Upstream, None).unwrap();
+    assert_eq!(
+      names_of(&ast),
+      vec!["OrderProduct", "Order", "ProductOrdered", "OrderHistory"]
+    );
+  }
+
+  #[test]
+  fn test_slice_downstream_with_depth() {
+    let ast = slice(&order_flow(), "ProductOrdered", SliceDirection::Downstream, Some(2)).unwrap();
+    assert_eq!(
+      names_of(&ast),
+      vec![
+        "ProductOrdered",
+        "ReserveInventoryPolicy",
+        "ReserveInventory",
+        "OrderHistory"
+      ]
+    );
+  }
+
+  #[test]
+  fn test_slice_both_leaves_out_siblings() {
+    let ast = parse(
+      br#"
+        c:OrderProduct
+        e:ProductOrdered
+        c:CancelOrder
+        e:OrderCancelled
+        r:OrderHistory
+        OrderProduct->ProductOrdered
+        ProductOrdered->OrderHistory
+        CancelOrder->OrderCancelled
+        OrderCancelled->OrderHistory
+        "#,
+    )
+    .unwrap();
+    let ast = slice(&ast, "ProductOrdered", SliceDirection::Both, None).unwrap();
+    assert_eq!(names_of(&ast), vec!["OrderProduct", "ProductOrdered", "OrderHistory"]);
+  }
+
+  #[test]
+  fn test_slice_unknown_focus() {
+    assert_eq!(
+      slice(&order_flow(), "Nowhere", SliceDirection::Both, None),
+      Err(SliceError::UnknownFocus("Nowhere".to_string()))
+    );
+  }
+}
@@ -0,0 +1,364 @@
+use std::collections::BTreeMap;
+
+use serde_derive::Deserialize;
+
+use crate::ast::name_type::NameType;
+use crate::ast::{Arrow, Ast, Attribute, Caption, Field, FieldType, Line, Name, Setting};
+use crate::parsers::{is_identifier, is_name};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StructuredFormat {
+  Json,
+  Yaml,
+  Toml,
+}
+
+impl StructuredFormat {
+  pub fn from_extension(extension: &str) -> Option<Self> {
+    match extension.to_lowercase().as_str() {
+      "json" => Some(StructuredFormat::Json),
+      "yaml" | "yml" => Some(StructuredFormat::Yaml),
+      "toml" => Some(StructuredFormat::Toml),
+      _ => None,
+    }
+  }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum CaptionSource {
+  Text(String),
+  Localized(BTreeMap<String, String>),
+}
+
+impl From<CaptionSource> for Caption {
+  fn from(source: CaptionSource) -> Self {
+    match source {
+      CaptionSource::Text(text) => Caption::new(text),
+      CaptionSource::Localized(texts) => texts.into_iter().fold(Caption::default(), |caption, (locale, text)| {
+        caption.with_locale(locale, text)
+      }),
+    }
+  }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FieldSource {
+  name: String,
+  #[serde(rename = "type")]
+  field_type: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ElementSource {
+  #[serde(rename = "type")]
+  element_type: String,
+  name: String,
+  caption: Option<CaptionSource>,
+  #[serde(default)]
+  tags: Vec<String>,
+  #[serde(default)]
+  attributes: BTreeMap<String, String>,
+  #[serde(default)]
+  fields: Vec<FieldSource>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum RelationshipKind {
+  Arrow,
+  Line,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RelationshipSource {
+  from: String,
+  to: String,
+  #[serde(default = "default_kind")]
+  kind: RelationshipKind,
+  caption: Option<CaptionSource>,
+}
+
+fn default_kind() -> RelationshipKind {
+  RelationshipKind::Arrow
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ModelSource {
+  #[serde(default)]
+  settings: BTreeMap<String, String>,
+  #[serde(default)]
+  include: Vec<String>,
+  #[serde(default)]
+  elements: Vec<ElementSource>,
+  #[serde(default)]
+  relationships: Vec<RelationshipSource>,
+}
+
+// Accepts the DSL prefixes as well as the type names, e.g. `e`, `event`, `Event`.
+pub fn parse_name_type(s: &str) -> Option<NameType> {
+  match s.to_lowercase().replace(['_', '-', ' '], "").as_str() {
+    "t" | "title" => Some(NameType::Title),
+    "u" | "user" | "actor" => Some(NameType::User),
+    "c" | "command" => Some(NameType::Command),
+    "e" | "event" => Some(NameType::Event),
+    "a" | "aggregate" => Some(NameType::Aggregate),
+    "p" | "policy" => Some(NameType::Policy),
+    "r" | "readmodel" => Some(NameType::ReadModel),
+    "h" | "hotspot" => Some(NameType::HotSpot),
+    _ => None,
+  }
+}
+
+fn parse_field_type(s: &str) -> Result<FieldType, String> {
+  let s = s.trim();
+  match s.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+    Some(element_type) => Ok(FieldType::List(Box::new(parse_field_type(element_type)?))),
+    None if is_identifier(s) => Ok(FieldType::Simple(s.to_string())),
+    None => Err(format!("invalid field type '{}'", s)),
+  }
+}
+
+fn element(index: usize, source: ElementSource) -> Result<Ast, String> {
+  let context = format!("elements[{}]", index);
+  let name_type = parse_name_type(&source.element_type)
+    .ok_or_else(|| format!("{}: unknown element type '{}'", context, source.element_type))?;
+  if source.name.trim().is_empty() {
+    return Err(format!("{}: name must not be empty", context));
+  }
+  if !is_name(&source.name) {
+    return Err(format!("{}: invalid name '{}'", context, source.name));
+  }
+  if let Some(tag) = source.tags.iter().find(|tag| !is_identifier(tag)) {
+    return Err(format!("{}: invalid tag '{}'", context, tag));
+  }
+  if let Some(key) = source.attributes.keys().find(|key| !is_identifier(key)) {
+    return Err(format!("{}: invalid attribute key '{}'", context, key));
+  }
+  if let Some(field) = source.fields.iter().find(|field| !is_identifier(&field.name)) {
+    return Err(format!("{}: invalid field name '{}'", context, field.name));
+  }
+  let fields = source
+    .fields
+    .into_iter()
+    .map(|field| {
+      parse_field_type(&field.field_type)
+        .map(|field_type| Field::new(field.name, field_type))
+        .map_err(|message| format!("{}: {}", context, message))
+    })
+    .collect::<Result<Vec<_>, _>>()?;
+  let name = Name {
+    name_type,
+    name: source.name,
+    caption: source.caption.map(Caption::from).unwrap_or_default(),
+    attributes: source
+      .attributes
+      .into_iter()
+      .map(|(key, value)| Attribute::new(key, value))
+      .collect(),
+    fields,
+    tags: source.tags,
+  };
+  Ok(match name.name_type {
+    NameType::Title => Ast::TitleDef(name),
+    _ => Ast::NameDef(name),
+  })
+}
+
+fn relationship(index: usize, source: RelationshipSource) -> Result<Ast, String> {
+  if source.from.trim().is_empty() || source.to.trim().is_empty() {
+    return Err(format!("relationships[{}]: from and to must not be empty", index));
+  }
+  if let Some(name) = [&source.from, &source.to].into_iter().find(|name| !is_name(name)) {
+    return Err(format!("relationships[{}]: invalid name '{}'", index, name));
+  }
+  let caption = source.caption.map(Caption::from).unwrap_or_default();
+  Ok(match source.kind {
+    RelationshipKind::Arrow => Ast::Arrow(Arrow::new(source.from, source.to, caption)),
+    RelationshipKind::Line => Ast::Line(Line::new(source.from, source.to, caption)),
+  })
+}
+
+impl ModelSource {
+  fn into_ast(self) -> Result<Ast, String> {
+    let mut documents = Vec::new();
+    for (key, value) in self.settings {
+      documents.push(Ast::SettingDef(Setting::new(key, value)));
+    }
+    documents.extend(self.include.into_iter().map(Ast::Include));
+    for (index, source) in self.elements.into_iter().enumerate() {
+      documents.push(element(index, source)?);
+    }
+    for (index, source) in self.relationships.into_iter().enumerate() {
+      documents.push(relationship(index, source)?);
+    }
+    Ok(Ast::Documents(documents))
+  }
+}
+
+pub fn parse(format: &StructuredFormat, input: &str) -> Result<Ast, String> {
+  let source: ModelSource = match format {
+    StructuredFormat::Json => serde_json::from_str(input).map_err(|error| error.to_string())?,
+    StructuredFormat::Yaml => serde_norway::from_str(input).map_err(|error| error.to_string())?,
+    StructuredFormat::Toml => toml::from_str(input).map_err(|error| error.to_string())?,
+  };
+  source.into_ast()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parsers;
+
+  const EXPECTED: &str = r#"
+    !direction TB
+    include "payments.eg"
+    c:OrderProduct:"Order product" @mvp
+    e:ProductOrdered:en"Product ordered":ja"注文された" {owner=team-a, orderId: OrderId, lines: [OrderLine]}
+    OrderProduct->ProductOrdered
+    ProductOrdered--OrderProduct:"1"
+  "#;
+
+  #[test]
+  fn test_parse_yaml() {
+    let ast = parse(
+      &StructuredFormat::Yaml,
+      r#"
+settings:
+  direction: TB
+include: [payments.eg]
+elements:
+  - {type: command, name: OrderProduct, caption: Order product, tags: [mvp]}
+  - type: e
+    name: ProductOrdered
+    caption: {en: Product ordered, ja: 注文された}
+    attributes: {owner: team-a}
+    fields:
+      - {name: orderId, type: OrderId}
+      - {name: lines, type: "[OrderLine]"}
+relationships:
+  - {from: OrderProduct, to: ProductOrdered}
+  - {from: ProductOrdered, to: OrderProduct, kind: line, caption: "1"}
+"#,
+    )
+    .unwrap();
+    assert_eq!(ast, parsers::parse(EXPECTED.as_bytes()).unwrap());
+  }
+
+  #[test]
+  fn test_parse_json_and_toml() {
+    let json = r#"{
+      "settings": {"direction": "TB"},
+      "include": ["payments.eg"],
+      "elements": [
+        {"type": "Command", "name": "OrderProduct", "caption": "Order product", "tags": ["mvp"]},
+        {"type": "event", "name": "ProductOrdered", "caption": {"en": "Product ordered", "ja": "注文された"},
+         "attributes": {"owner": "team-a"},
+         "fields": [{"name": "orderId", "type": "OrderId"}, {"name": "lines", "type": "[OrderLine]"}]}
+      ],
+      "relationships": [
+        {"from": "OrderProduct", "to": "ProductOrdered"},
+        {"from": "ProductOrdered", "to": "OrderProduct", "kind": "line", "caption": "1"}
+      ]
+    }"#;
+    let toml = r#"
+include = ["payments.eg"]
+
+[settings]
+direction = "TB"
+
+[[elements]]
+type = "c"
+name = "OrderProduct"
+caption = "Order product"
+tags = ["mvp"]
+
+[[elements]]
+type = "event"
+name = "ProductOrdered"
+caption = { en = "Product ordered", ja = "注文された" }
+attributes = { owner = "team-a" }
+fields = [{ name = "orderId", type = "OrderId" }, { name = "lines", type = "[OrderLine]" }]
+
+[[relationships]]
+from = "OrderProduct"
+to = "ProductOrdered"
+
+[[relationships]]
+from = "ProductOrdered"
+to = "OrderProduct"
+kind = "line"
+caption = "1"
+"#;
+    let expected = parsers::parse(EXPECTED.as_bytes()).unwrap();
+    assert_eq!(parse(&StructuredFormat::Json, json).unwrap(), expected);
+    assert_eq!(parse(&StructuredFormat::Toml, toml).unwrap(), expected);
+  }
+
+  #[test]
+  fn test_parse_errors() {
+    assert_eq!(
+      parse(&StructuredFormat::Yaml, "elements:\n  - {type: sticky, name: A}\n").unwrap_err(),
+      "elements[0]: unknown element type 'sticky'"
+    );
+    assert_eq!(
+      parse(
+        &StructuredFormat::Json,
+        r#"{"relationships": [{"from": "A", "to": ""}]}"#
+      )
+      .unwrap_err(),
+      "relationships[0]: from and to must not be empty"
+    );
+    assert_eq!(
+      parse(
+        &StructuredFormat::Yaml,
+        "elements:\n  - {type: e, name: 'Order: placed'}\n"
+      )
+      .unwrap_err(),
+      "elements[0]: invalid name 'Order: placed'"
+    );
+    assert_eq!(
+      parse(&StructuredFormat::Yaml, "elements:\n  - {type: e, name: ' Placed'}\n").unwrap_err(),
+      "elements[0]: invalid name ' Placed'"
+    );
+    assert_eq!(
+      parse(
+        &StructuredFormat::Yaml,
+        "elements:\n  - {type: e, name: Placed, tags: [team a]}\n"
+      )
+      .unwrap_err(),
+      "elements[0]: invalid tag 'team a'"
+    );
+    assert_eq!(
+      parse(
+        &StructuredFormat::Yaml,
+        "elements:\n  - {type: e, name: Placed, fields: [{name: 'order id', type: OrderId}]}\n"
+      )
+      .unwrap_err(),
+      "elements[0]: invalid field name 'order id'"
+    );
+    assert_eq!(
+      parse(
+        &StructuredFormat::Yaml,
+        "elements:\n  - {type: e, name: Placed, fields: [{name: orderId, type: '[注文]'}]}\n"
+      )
+      .unwrap_err(),
+      "elements[0]: invalid field type '注文'"
+    );
+    assert_eq!(
+      parse(
+        &StructuredFormat::Json,
+        r#"{"relationships": [{"from": "A", "to": "B->C"}]}"#
+      )
+      .unwrap_err(),
+      "relationships[0]: invalid name 'B->C'"
+    );
+    assert!(parse(&StructuredFormat::Json, r#"{"elements": [{"type": "e"}]}"#)
+      .unwrap_err()
+      .contains("missing field `name`"));
+  }
+}
@@ -0,0 +1,137 @@
+use std::str::FromStr;
+
+use oni_comb_parser_rs::prelude::*;
+
+use crate::ast::Name;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TagExpr {
+  Tag(String),
+  Attribute(String, String),
+  Not(Box<TagExpr>),
+  And(Box<TagExpr>, Box<TagExpr>),
+  Or(Box<TagExpr>, Box<TagExpr>),
+}
+
+impl TagExpr {
+  fn and(lhs: TagExpr, rhs: TagExpr) -> TagExpr {
+    TagExpr::And(Box::new(lhs), Box::new(rhs))
+  }
+
+  fn or(lhs: TagExpr, rhs: TagExpr) -> TagExpr {
+    TagExpr::Or(Box::new(lhs), Box::new(rhs))
+  }
+
+  pub fn matches(&self, name: &Name) -> bool {
+    match self {
+      TagExpr::Tag(tag) => name.tags.contains(tag),
+      TagExpr::Attribute(key, value) => name.attribute(key) == Some(value.as_str()),
+      TagExpr::Not(expr) => !expr.matches(name),
+      TagExpr::And(lhs, rhs) => lhs.matches(name) && rhs.matches(name),
+      TagExpr::Or(lhs, rhs) => lhs.matches(name) || rhs.matches(name),
+    }
+  }
+}
+
+impl FromStr for TagExpr {
+  type Err = ();
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    (expr() - space() - end())
+      .parse(s.as_bytes())
+      .to_result()
+      .map_err(|_| ())
+  }
+}
+
+fn space<'a>() -> Parser<'a, u8, ()> {
+  elm_of(b" \t").of_many0().discard()
+}
+
+fn word<'a>() -> Parser<'a, u8, String> {
+  (elm_alpha_digit_ref() | elm_ref_of(b"_-."))
+    .map(Clone::clone)
+    .of_many1()
+    .map_res(String::from_utf8)
+}
+
+// `key=value` matches elements whose attribute `key` has that value.
+fn attribute<'a>() -> Parser<'a, u8, TagExpr> {
+  let p = space() * word() - space() - elm_ref(b'=') - space() + word();
+  p.map(|(key, value)| TagExpr::Attribute(key, value))
+}
+
+fn tag<'a>() -> Parser<'a, u8, TagExpr> {
+  (space() * elm_ref(b'@').opt() * word()).map(TagExpr::Tag)
+}
+
+fn primary<'a>() -> Parser<'a, u8, TagExpr> {
+  let group = space() * elm_ref(b'(') * lazy(expr) - space() - elm_ref(b')');
+  group.attempt() | attribute().attempt() | tag()
+}
+
+fn unary<'a>() -> Parser<'a, u8, TagExpr> {
+  let not = space() * elm_ref(b'!') * lazy(unary);
+  not.map(|expr| TagExpr::Not(Box::new(expr))).attempt() | primary()
+}
+
+fn and_expr<'a>() -> Parser<'a, u8, TagExpr> {
+  let op = space() * elm_ref(b'&').map(|_| TagExpr::and as fn(TagExpr, TagExpr) -> TagExpr);
+  unary().chain_left1(op.attempt())
+}
+
+fn expr<'a>() -> Parser<'a, u8, TagExpr> {
+  let op = space() * elm_ref(b'|').map(|_| TagExpr::or as fn(TagExpr, TagExpr) -> TagExpr);
+  and_expr().chain_left1(op.attempt())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::ast::{Attribute, Caption};
+
+  fn tags(tags: &[&str]) -> Name {
+    Name::of_event("OrderPlaced".to_string(), Caption::default())
+      .with_tags(tags.iter().map(|tag| tag.to_string()).collect())
+  }
+
+  #[test]
+  fn test_from_str() {
+    assert_eq!(
+      TagExpr::from_str("@mvp & !(phase2 | team-payments)"),
+      Ok(TagExpr::and(
+        TagExpr::Tag("mvp".to_string()),
+        TagExpr::Not(Box::new(TagExpr::or(
+          TagExpr::Tag("phase2".to_string()),
+          TagExpr::Tag("team-payments".to_string()),
+        ))),
+      ))
+    );
+    assert_eq!(TagExpr::from_str("mvp &"), Err(()));
+  }
+
+  #[test]
+  fn test_matches() {
+    let expr = TagExpr::from_str("mvp & !phase2 | team-payments").unwrap();
+    assert!(expr.matches(&tags(&["mvp"])));
+    assert!(!expr.matches(&tags(&["mvp", "phase2"])));
+    assert!(expr.matches(&tags(&["phase2", "team-payments"])));
+    assert!(!expr.matches(&tags(&[])));
+  }
+
+  #[test]
+  fn test_matches_attributes() {
+    let expr = TagExpr::from_str("owner = team-a & !status=draft").unwrap();
+    let name = |attributes: &[(&str, &str)]| {
+      tags(&[]).with_attributes(
+        attributes
+          .iter()
+          .map(|(key, value)| Attribute::new(key.to_string(), value.to_string()))
+          .collect(),
+      )
+    };
+    assert!(expr.matches(&name(&[("owner", "team-a")])));
+    assert!(!expr.matches(&name(&[("owner", "team-a"), ("status", "draft")])));
+    assert!(!expr.matches(&name(&[("owner", "team-b")])));
+  }
+}